@@ -0,0 +1,309 @@
+//! Service ABI descriptor
+//!
+//! The entry macros of jade emit fragments of the ABI as `JADE_ABI_*`
+//! statics, they are collected from the symbol table of the built ELF and
+//! appended to the conventional metadata of the blob.
+//!
+//! The appended section is `ABI_MAGIC`, the format version, the length of
+//! the JSON descriptor as a little-endian `u32`, and the JSON itself.
+
+use anyhow::{Result, anyhow};
+use jam_codec::Decode;
+use jam_program_blob::ConventionalMetadata;
+use object::{Object, ObjectSection, ObjectSymbol};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The prefix of the ABI fragment symbols
+const SYMBOL_PREFIX: &str = "JADE_ABI_";
+
+/// The magic of the ABI section in the metadata
+const ABI_MAGIC: &[u8; 4] = b"jabi";
+
+/// The format version of the ABI section
+const ABI_VERSION: u8 = 1;
+
+/// ABI descriptor of a service
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Abi {
+    /// The jade version the service is built with
+    pub version: String,
+
+    /// The type of the authorizer config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+
+    /// The type of the refine payload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    /// The meaning of the accumulate output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// The storage layout
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<Storage>,
+
+    /// The type definitions
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<TypeDef>,
+}
+
+/// A storage entry
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Storage {
+    /// The hex encoded storage key
+    pub key: String,
+
+    /// The type of the value
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// A type definition
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypeDef {
+    /// The name of the type
+    pub name: String,
+
+    /// The fields of a struct
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+
+    /// The variants of an enum
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<Variant>,
+
+    /// The aliased type of a type alias
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+/// A variant of an enum
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Variant {
+    /// The name of the variant
+    pub name: String,
+
+    /// The fields of the variant
+    pub fields: Vec<Field>,
+}
+
+/// A field of a struct or a variant
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    /// The name of the field, or its index in tuples
+    pub name: String,
+
+    /// The type of the field
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+impl Abi {
+    /// Collect the ABI fragments from a RISC-V ELF
+    ///
+    /// Returns `None` if the ELF does not contain any fragment.
+    pub fn from_elf(elf: &[u8]) -> Result<Option<Self>> {
+        let file = object::File::parse(elf)?;
+        let mut visited = BTreeSet::new();
+        let mut abi = None;
+        for symbol in file.symbols().chain(file.dynamic_symbols()) {
+            let Ok(name) = symbol.name() else {
+                continue;
+            };
+
+            if !name.starts_with(SYMBOL_PREFIX) || !visited.insert(name.to_string()) {
+                continue;
+            }
+
+            let index = symbol
+                .section_index()
+                .ok_or_else(|| anyhow!("abi fragment {name} is not defined"))?;
+            let data = file
+                .section_by_index(index)?
+                .data_range(symbol.address(), symbol.size())?
+                .ok_or_else(|| anyhow!("abi fragment {name} is out of its section"))?;
+            let fragment: Abi = serde_json::from_slice(data)?;
+            abi.get_or_insert_with(Abi::default).merge(fragment);
+        }
+
+        Ok(abi)
+    }
+
+    /// Read the ABI from the metadata of a blob
+    ///
+    /// Returns `None` if the metadata only contains the crate info.
+    pub fn from_metadata(mut metadata: &[u8]) -> Result<Option<Self>> {
+        ConventionalMetadata::decode(&mut metadata)
+            .map_err(|e| anyhow!("failed to decode the metadata: {e:?}"))?;
+        if metadata.is_empty() {
+            return Ok(None);
+        }
+
+        let Some(rest) = metadata.strip_prefix(ABI_MAGIC) else {
+            return Err(anyhow!("unknown section after the metadata"));
+        };
+
+        let (&version, rest) = rest
+            .split_first()
+            .ok_or_else(|| anyhow!("missing the abi version"))?;
+        if version != ABI_VERSION {
+            return Err(anyhow!("unsupported abi version {version}"));
+        }
+
+        let (len, json) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| anyhow!("missing the abi length"))?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if json.len() != len {
+            return Err(anyhow!(
+                "abi length mismatch: expected {len} bytes, got {}",
+                json.len()
+            ));
+        }
+
+        serde_json::from_slice(json).map(Some).map_err(Into::into)
+    }
+
+    /// Encode the ABI for appending it to the metadata
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)?;
+        let len = u32::try_from(json.len()).map_err(|_| anyhow!("abi is too large"))?;
+        let mut encoded = ABI_MAGIC.to_vec();
+        encoded.push(ABI_VERSION);
+        encoded.extend(len.to_le_bytes());
+        encoded.extend(json);
+        Ok(encoded)
+    }
+
+    /// Merge a fragment into the ABI
+    pub fn merge(&mut self, fragment: Abi) {
+        if self.version.is_empty() {
+            self.version = fragment.version;
+        }
+
+        self.config = self.config.take().or(fragment.config);
+        self.payload = self.payload.take().or(fragment.payload);
+        self.output = self.output.take().or(fragment.output);
+        self.storage.extend(fragment.storage);
+        self.types.extend(fragment.types);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jam_codec::Encode;
+    use jam_program_blob::CrateInfo;
+    use object::{
+        Architecture, BinaryFormat, Endianness, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+        write::{Object, Symbol, SymbolSection},
+    };
+
+    /// Build a RISC-V ELF with the given ABI fragments
+    fn elf(fragments: &[(&str, &str)]) -> Vec<u8> {
+        let mut obj = Object::new(BinaryFormat::Elf, Architecture::Riscv64, Endianness::Little);
+        let section = obj.add_section(Vec::new(), b".rodata".to_vec(), SectionKind::ReadOnlyData);
+        for (name, json) in fragments {
+            let offset = obj.append_section_data(section, json.as_bytes(), 1);
+            obj.add_symbol(Symbol {
+                name: name.as_bytes().to_vec(),
+                value: offset,
+                size: json.len() as u64,
+                kind: SymbolKind::Data,
+                scope: SymbolScope::Linkage,
+                weak: false,
+                section: SymbolSection::Section(section),
+                flags: SymbolFlags::None,
+            });
+        }
+        obj.write().unwrap()
+    }
+
+    /// The metadata of a test crate
+    fn metadata() -> Vec<u8> {
+        ConventionalMetadata::Info(CrateInfo {
+            name: "token".into(),
+            version: "0.1.0".into(),
+            license: "GPL-3.0".into(),
+            authors: vec![],
+        })
+        .encode()
+    }
+
+    #[test]
+    fn from_elf_merges_fragments() {
+        let elf = self::elf(&[
+            (
+                "JADE_ABI_REFINE_0000000000000001",
+                r#"{"version":"0.0.15","payload":"Vec<Instruction>"}"#,
+            ),
+            (
+                "JADE_ABI_TYPE_HOLDERS_0000000000000002",
+                r#"{"version":"0.0.15","types":[{"name":"Holders","alias":"u64"}]}"#,
+            ),
+            (
+                "JADE_ABI_TYPE_HOLDERS_0000000000000003",
+                r#"{"version":"0.0.15","types":[{"name":"Holders","alias":"u32"}]}"#,
+            ),
+            ("OTHER_SYMBOL", "not json"),
+        ]);
+
+        let abi = Abi::from_elf(&elf).unwrap().unwrap();
+        assert_eq!(abi.version, "0.0.15");
+        assert_eq!(abi.payload.as_deref(), Some("Vec<Instruction>"));
+        assert_eq!(abi.types.len(), 2);
+    }
+
+    #[test]
+    fn from_elf_without_fragments() {
+        let elf = self::elf(&[("OTHER_SYMBOL", "not json")]);
+        assert_eq!(Abi::from_elf(&elf).unwrap(), None);
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let elf = self::elf(&[(
+            "JADE_ABI_STORAGE_HOLDERS_0000000000000001",
+            r#"{"version":"0.0.15","storage":[{"key":"686f6c64657273","type":"Holders"}]}"#,
+        )]);
+        let abi = Abi::from_elf(&elf).unwrap().unwrap();
+
+        let mut metadata = self::metadata();
+        assert_eq!(Abi::from_metadata(&metadata).unwrap(), None);
+
+        metadata.extend(abi.encode().unwrap());
+        assert_eq!(Abi::from_metadata(&metadata).unwrap(), Some(abi));
+    }
+
+    #[test]
+    fn metadata_rejects_bad_sections() {
+        let abi = Abi {
+            version: "0.0.15".into(),
+            ..Default::default()
+        };
+        let encoded = abi.encode().unwrap();
+
+        // truncated descriptor
+        let mut metadata = self::metadata();
+        metadata.extend(&encoded[..encoded.len() - 1]);
+        assert!(Abi::from_metadata(&metadata).is_err());
+
+        // unknown version
+        let mut metadata = self::metadata();
+        let mut unknown = encoded.clone();
+        unknown[ABI_MAGIC.len()] = ABI_VERSION + 1;
+        metadata.extend(unknown);
+        assert!(Abi::from_metadata(&metadata).is_err());
+
+        // raw json without the section header
+        let mut metadata = self::metadata();
+        metadata.extend(serde_json::to_vec(&abi).unwrap());
+        assert!(Abi::from_metadata(&metadata).is_err());
+    }
+}
@@ -6,9 +6,13 @@
 // If you update this, you should also update the toolchain installed by .github/workflows/rust.yml
 const TOOLCHAIN: &str = "1.90.0";
 
-use crate::manifest;
+use crate::{abi::Abi, manifest};
 use jam_codec::Encode;
 use jam_program_blob::{ConventionalMetadata, CoreVmProgramBlob, ProgramBlob};
+use polkavm_common::{
+    program::{BLOB_LEN_OFFSET, BLOB_LEN_SIZE, BlobLen, SECTION_END_OF_FILE},
+    varint::{self, MAX_VARINT_LENGTH},
+};
 use polkavm_linker::ProgramParts;
 use std::{
     borrow::Cow,
@@ -195,9 +199,6 @@ pub fn build_pvm_blob(
 
     // Post processing
     println!("Converting RISC-V ELF to PVM blob...");
-    let mut config = polkavm_linker::Config::default();
-    config.set_strip(true);
-    config.set_dispatch_table(blob_type.dispatch_table());
 
     let input_root = &out_dir.join(target_name).join(profile.as_str());
     let input_path_bin = input_root.join(&info.name);
@@ -225,16 +226,31 @@ pub fn build_pvm_blob(
 
     let orig =
         fs::read(&input_path).unwrap_or_else(|e| panic!("Failed to read {input_path:?} :{e:?}"));
-    let linked = polkavm_linker::program_from_elf(config, orig.as_ref())
+    let mut config = polkavm_linker::Config::default();
+    config.set_strip(false);
+    config.set_dispatch_table(blob_type.dispatch_table());
+    let full = polkavm_linker::program_from_elf(config, orig.as_ref())
         .expect("Failed to link pvm program:");
+    let linked = self::strip(&full);
 
-    // Write out a full `.pvm` blob for debugging/inspection.
+    // Write out a full `.pvm` blob for debugging/inspection, keeping the
+    // function names of the ELF symbol table for the gas profiles.
     let jam_out = out_dir.join("jam");
     fs::create_dir_all(&jam_out).expect("Failed to create jam directory");
     let output_path_pvm = jam_out.join(format!("{}.pvm", &info.name));
-    fs::write(output_path_pvm, &linked).expect("Error writing resulting binary");
+    fs::write(output_path_pvm, &full).expect("Error writing resulting binary");
     let name = info.name.clone();
-    let metadata = ConventionalMetadata::Info(info).encode().into();
+    let mut metadata = ConventionalMetadata::Info(info).encode();
+
+    // Embed the ABI collected from the entry macros.
+    if let Some(abi) = Abi::from_elf(&orig).expect("Failed to collect the service ABI") {
+        let output_path_abi = jam_out.join(format!("{name}.abi.json"));
+        let json = serde_json::to_vec_pretty(&abi).expect("Error serializing the ABI");
+        fs::write(output_path_abi, json).expect("Error writing the ABI");
+        metadata.extend(abi.encode().expect("Error encoding the ABI"));
+    }
+
+    let metadata = metadata.into();
     let output_file = blob_type.output_file(&jam_out, &name);
     if !matches!(blob_type, BlobType::CoreVmGuest) {
         let parts = polkavm_linker::ProgramParts::from_bytes(linked.into())
@@ -264,6 +280,33 @@ macro_rules! pvm_binary {
     };
 }
 
+/// Strip the debug sections off a linked program
+///
+/// The linker appends the debug info as optional sections after the code, so
+/// the stripped program is the linked one cut before them.
+fn strip(linked: &[u8]) -> Vec<u8> {
+    let parts = ProgramParts::from_bytes(linked.into())
+        .expect("failed to deserialize linked PolkaVM program");
+    let debug = [
+        &parts.debug_strings,
+        &parts.debug_line_programs,
+        &parts.debug_line_program_ranges,
+    ]
+    .into_iter()
+    .filter(|section| !section.is_empty())
+    .map(|section| {
+        let mut varint = [0; MAX_VARINT_LENGTH];
+        1 + varint::write_varint(section.len() as u32, &mut varint) + section.len()
+    })
+    .sum::<usize>();
+
+    let mut stripped = linked[..linked.len() - debug - 1].to_vec();
+    stripped.push(SECTION_END_OF_FILE);
+    let len = (stripped.len() as BlobLen).to_le_bytes();
+    stripped[BLOB_LEN_OFFSET..BLOB_LEN_OFFSET + BLOB_LEN_SIZE].copy_from_slice(&len);
+    stripped
+}
+
 fn to_blob<'a>(parts: &'a ProgramParts, metadata: Cow<'a, [u8]>) -> ProgramBlob<'a> {
     // Pad RO section with zeroes.
     let mut ro_data = parts.ro_data.to_vec();
@@ -284,3 +327,31 @@ fn to_blob<'a>(parts: &'a ProgramParts, metadata: Cow<'a, [u8]>) -> ProgramBlob<
         stack_size: parts.stack_size,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use polkavm_common::{
+        program::{SECTION_OPT_DEBUG_LINE_PROGRAMS, SECTION_OPT_DEBUG_STRINGS},
+        writer::ProgramBlobBuilder,
+    };
+
+    /// A program with the given debug sections
+    fn program(debug: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = ProgramBlobBuilder::new_64bit();
+        builder.set_ro_data(vec![1; 16]);
+        for (section, contents) in debug {
+            builder.add_custom_section(*section, contents.clone());
+        }
+        builder.to_vec().unwrap()
+    }
+
+    #[test]
+    fn strip_the_debug_sections() {
+        let full = self::program(&[
+            (SECTION_OPT_DEBUG_STRINGS, vec![2; 200]),
+            (SECTION_OPT_DEBUG_LINE_PROGRAMS, vec![3; 3]),
+        ]);
+        assert_eq!(super::strip(&full), self::program(&[]));
+        assert_eq!(super::strip(&self::program(&[])), self::program(&[]));
+    }
+}
@@ -2,11 +2,13 @@
 #![deny(missing_docs)]
 
 pub use {
+    abi::Abi,
     clap,
     manifest::{ModuleType, Profile},
     util::build,
 };
 
+pub mod abi;
 pub mod builder;
 pub mod cmd;
 pub mod manifest;
@@ -35,5 +35,9 @@ pub fn build(package: &str, module: Option<ModuleType>) -> Result<()> {
     println!("Copying service to OUT_DIR: {}", service.display());
     fs::copy(&binary, &service)?;
     fs::copy(&binary, jam.join(name))?;
+
+    // copy the debug blob for the symbols of the gas profiles
+    let name = format!("{package}.pvm");
+    fs::copy(parget.join("jam").join(&name), jam.join(name))?;
     Ok(())
 }
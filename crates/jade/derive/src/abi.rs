@@ -0,0 +1,209 @@
+//! ABI descriptor impl
+//!
+//! The entry macros emit fragments of the service ABI as JSON statics on the
+//! riscv target, `cjam` collects them from the symbol table of the built ELF
+//! and embeds the merged descriptor in the blob metadata.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::ToTokens;
+use std::{
+    env,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+use syn::{Fields, Item, LitByteStr, LitStr, parse_macro_input};
+
+/// The version of jade
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Describe a type in the service ABI
+pub fn abi(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as Item);
+    let (ident, def) = match self::type_def(&item) {
+        Ok(def) => def,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let fragment = self::fragment(&format!("type_{ident}"), &[("types", format!("[{def}]"))]);
+    quote::quote! {
+        #item
+        #fragment
+    }
+    .into()
+}
+
+/// Describe a storage entry in the service ABI
+pub fn storage(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as Item);
+    let mut key = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("key") {
+            let value = meta.value()?;
+            key = Some(if value.peek(LitByteStr) {
+                value.parse::<LitByteStr>()?.value()
+            } else {
+                value.parse::<LitStr>()?.value().into_bytes()
+            });
+            Ok(())
+        } else {
+            Err(meta.error("unsupported storage property"))
+        }
+    });
+    parse_macro_input!(args with parser);
+
+    let Some(key) = key else {
+        return syn::Error::new(Span::call_site(), "missing storage key")
+            .to_compile_error()
+            .into();
+    };
+
+    let ident = match self::ident(&item) {
+        Ok(ident) => ident,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let hex = key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    let entry = format!(
+        "[{{\"key\":{},\"type\":{}}}]",
+        self::string(&hex),
+        self::string(&ident.to_string())
+    );
+    let fragment = self::fragment(&format!("storage_{ident}"), &[("storage", entry)]);
+    quote::quote! {
+        #item
+        #fragment
+    }
+    .into()
+}
+
+/// Emit a fragment of the service ABI
+///
+/// The fields are pairs of keys and raw JSON values. The symbol is suffixed
+/// with a hash of the crate, the source path and line of the item and the
+/// fragment, so items of the same name in different modules don't collide
+/// and the symbol is stable across builds.
+pub fn fragment(name: &str, fields: &[(&str, String)]) -> proc_macro2::TokenStream {
+    let mut json = format!("{{\"version\":{}", self::string(VERSION));
+    for (key, value) in fields {
+        json.push_str(&format!(",{}:{value}", self::string(key)));
+    }
+    json.push('}');
+
+    let mut hasher = DefaultHasher::new();
+    env::var("CARGO_CRATE_NAME")
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    let site = proc_macro::Span::call_site();
+    site.file().hash(&mut hasher);
+    site.line().hash(&mut hasher);
+    json.hash(&mut hasher);
+    let ident = quote::format_ident!("JADE_ABI_{}_{:016X}", name.to_uppercase(), hasher.finish());
+    let len = json.len();
+    let bytes = LitByteStr::new(json.as_bytes(), Span::call_site());
+    quote::quote! {
+        #[cfg(target_arch = "riscv64")]
+        #[doc(hidden)]
+        #[used]
+        #[unsafe(no_mangle)]
+        pub static #ident: [u8; #len] = *#bytes;
+    }
+}
+
+/// Get the name of a type as a JSON string
+pub fn type_name(ty: &impl ToTokens) -> String {
+    let name = ty
+        .to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" :: ", "::")
+        .replace(" ,", ",")
+        .replace("& ", "&");
+    self::string(&name)
+}
+
+/// Encode a JSON string
+pub fn string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Get the ident of a type item
+fn ident(item: &Item) -> syn::Result<&syn::Ident> {
+    match item {
+        Item::Struct(item) => Ok(&item.ident),
+        Item::Enum(item) => Ok(&item.ident),
+        Item::Type(item) => Ok(&item.ident),
+        item => Err(syn::Error::new_spanned(item, "expected a type definition")),
+    }
+}
+
+/// Describe the definition of a type
+fn type_def(item: &Item) -> syn::Result<(&syn::Ident, String)> {
+    let ident = self::ident(item)?;
+    let def = match item {
+        Item::Struct(item) => format!(
+            "{{\"name\":{},\"fields\":{}}}",
+            self::string(&ident.to_string()),
+            self::fields(&item.fields)
+        ),
+        Item::Enum(item) => {
+            let variants = item
+                .variants
+                .iter()
+                .map(|variant| {
+                    format!(
+                        "{{\"name\":{},\"fields\":{}}}",
+                        self::string(&variant.ident.to_string()),
+                        self::fields(&variant.fields)
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "{{\"name\":{},\"variants\":[{}]}}",
+                self::string(&ident.to_string()),
+                variants.join(",")
+            )
+        }
+        Item::Type(item) => format!(
+            "{{\"name\":{},\"alias\":{}}}",
+            self::string(&ident.to_string()),
+            self::type_name(&item.ty)
+        ),
+        _ => unreachable!("checked by ident"),
+    };
+
+    Ok((ident, def))
+}
+
+/// Describe the fields of a struct or a variant
+fn fields(fields: &Fields) -> String {
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let name = field
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string())
+                .unwrap_or_else(|| index.to_string());
+            format!(
+                "{{\"name\":{},\"type\":{}}}",
+                self::string(&name),
+                self::type_name(&field.ty)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", fields.join(","))
+}
@@ -1,15 +1,33 @@
 //! authorize interface impl
 
+use crate::abi;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{LitStr, parse_macro_input};
 
 /// Implement the is_authorized interface
 ///
 /// 1. wrap the function with a C-compatible function
 /// 2. impl with polkavm-derive-impl
-pub fn accumulate(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn accumulate(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut output = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("output") {
+            output = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("unsupported accumulate property"))
+        }
+    });
+    parse_macro_input!(args with parser);
+
     let fun = parse_macro_input!(input as syn::ItemFn);
     let funame = fun.sig.ident.clone();
+    let fragment = abi::fragment(
+        "accumulate",
+        &output
+            .map(|output| vec![("output", abi::string(&output))])
+            .unwrap_or_default(),
+    );
 
     // construct the export
     //
@@ -29,6 +47,8 @@ pub fn accumulate(_args: TokenStream, input: TokenStream) -> TokenStream {
                 (0, 0)
             }
         }
+
+        #fragment
     }
     .into()
 }
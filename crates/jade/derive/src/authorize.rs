@@ -1,31 +1,114 @@
 //! authorize interface impl
 
+use crate::abi;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{FnArg, ItemFn, ReturnType, Type, parse_macro_input};
 
 /// Implement the is_authorized interface
 ///
 /// 1. wrap the function with a C-compatible function
 /// 2. impl with polkavm-derive-impl
-pub fn is_authorized(_args: TokenStream, input: TokenStream) -> TokenStream {
+///
+/// The function takes either the `CoreIndex` or an `AuthContext`, and
+/// returns either an `AuthTrace` or a `Result<AuthTrace, E>`, an error
+/// means the work package is not authorized.
+pub fn is_authorized(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut config = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("config") {
+            config = Some(meta.value()?.parse::<Type>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported is_authorized property"))
+        }
+    });
+    parse_macro_input!(args with parser);
+
     let fun = parse_macro_input!(input as syn::ItemFn);
     let funame = fun.sig.ident.clone();
+    let fragment = abi::fragment(
+        "is_authorized",
+        &config
+            .map(|ty| vec![("config", abi::type_name(&ty))])
+            .unwrap_or_default(),
+    );
+
+    // pass the auth context if it is required
+    let call = if self::takes_context(&fun) {
+        quote::quote! {
+            let context = match jade::auth::AuthContext::fetch(core_index) {
+                Ok(context) => context,
+                Err(e) => {
+                    jade::error!("not authorized: failed to fetch auth context: {:?}", e);
+                    panic!("failed to fetch auth context");
+                }
+            };
+            let result = #funame(context);
+        }
+    } else {
+        quote::quote! {
+            let result = #funame(core_index);
+        }
+    };
+
+    // unwrap the trace if the function is fallible
+    let trace = if self::returns_result(&fun) {
+        quote::quote! {
+            let result = match result {
+                Ok(trace) => trace,
+                Err(e) => {
+                    jade::error!("not authorized: {:?}", e);
+                    panic!("not authorized");
+                }
+            };
+        }
+    } else {
+        Default::default()
+    };
 
     // construct the export
-    //
-    // TODO: introduce params check
     quote::quote! {
         #[jade::polkavm_derive::polkavm_export(abi = jade::polkavm_derive::default_abi)]
         extern "C" fn jade_is_authorized(ptr: u32, size: u32) -> (u64, u64) {
             #fun
 
             let buf = unsafe { core::slice::from_raw_parts(ptr as *const u8, size as usize) };
-            let core_index: CoreIndex =
+            let core_index: jade::prelude::CoreIndex =
                  jade::codec::decode(buf).inspect_err(|e| jade::error!("decoded is_authorized parameters: {:?}", e))
                      .expect("failed to decode is_authorized parameters");
-            let result = #funame(core_index);
+            #call
+            #trace
             ((&result).as_ptr() as u64, result.len() as u64)
         }
+
+        #fragment
     }
     .into()
 }
+
+/// If the function takes the auth context as its argument
+fn takes_context(fun: &ItemFn) -> bool {
+    let Some(FnArg::Typed(arg)) = fun.sig.inputs.first() else {
+        return false;
+    };
+
+    self::last_ident(&arg.ty).is_some_and(|ident| ident == "AuthContext")
+}
+
+/// If the function returns a result
+fn returns_result(fun: &ItemFn) -> bool {
+    let ReturnType::Type(_, ty) = &fun.sig.output else {
+        return false;
+    };
+
+    self::last_ident(ty).is_some_and(|ident| ident == "Result")
+}
+
+/// Get the last ident of a type path
+fn last_ident(ty: &Type) -> Option<&syn::Ident> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    path.path.segments.last().map(|segment| &segment.ident)
+}
@@ -2,12 +2,16 @@
 
 use proc_macro::TokenStream;
 
+mod abi;
 mod accumulate;
 mod authorize;
 mod refine;
 
 /// Export the is_authorized interface
 ///
+/// Use `#[jade::is_authorized(config = T)]` to describe the authorizer
+/// config in the service ABI.
+///
 /// TODO: replace the function body directly
 #[proc_macro_attribute]
 pub fn is_authorized(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -15,13 +19,33 @@ pub fn is_authorized(args: TokenStream, input: TokenStream) -> TokenStream {
 }
 
 /// Export the refine interface
+///
+/// Use `#[jade::refine(payload = T)]` to describe the payload in the
+/// service ABI.
 #[proc_macro_attribute]
 pub fn refine(args: TokenStream, input: TokenStream) -> TokenStream {
     refine::refine(args, input)
 }
 
 /// Export the accumulate interface
+///
+/// Use `#[jade::accumulate(output = "...")]` to describe the meaning of
+/// the accumulate output in the service ABI.
 #[proc_macro_attribute]
 pub fn accumulate(args: TokenStream, input: TokenStream) -> TokenStream {
     accumulate::accumulate(args, input)
 }
+
+/// Describe the definition of a type in the service ABI
+#[proc_macro_attribute]
+pub fn abi(args: TokenStream, input: TokenStream) -> TokenStream {
+    abi::abi(args, input)
+}
+
+/// Describe a storage entry in the service ABI
+///
+/// e.g. `#[jade::storage(key = "holders")]`
+#[proc_macro_attribute]
+pub fn storage(args: TokenStream, input: TokenStream) -> TokenStream {
+    abi::storage(args, input)
+}
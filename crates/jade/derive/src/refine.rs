@@ -1,15 +1,33 @@
 //! refine interface impl
 
+use crate::abi;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{Type, parse_macro_input};
 
 /// Implement the is_authorized interface
 ///
 /// 1. wrap the function with a C-compatible function
 /// 2. impl with polkavm-derive-impl
-pub fn refine(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn refine(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut payload = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("payload") {
+            payload = Some(meta.value()?.parse::<Type>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported refine property"))
+        }
+    });
+    parse_macro_input!(args with parser);
+
     let fun = parse_macro_input!(input as syn::ItemFn);
     let funame = fun.sig.ident.clone();
+    let fragment = abi::fragment(
+        "refine",
+        &payload
+            .map(|ty| vec![("payload", abi::type_name(&ty))])
+            .unwrap_or_default(),
+    );
 
     // construct the export
     //
@@ -25,6 +43,8 @@ pub fn refine(_args: TokenStream, input: TokenStream) -> TokenStream {
             let result = #funame(core, index, id, payload, package);
             ((&result).as_ptr() as u64, result.len() as u64)
         }
+
+        #fragment
     }
     .into()
 }
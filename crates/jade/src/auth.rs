@@ -0,0 +1,46 @@
+//! Context of the is_authorized invocation
+
+use crate::{
+    host::fetch,
+    prelude::{CoreIndex, Vec, WorkPackage},
+};
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+
+/// Context of the is_authorized invocation
+///
+/// Accept it as the argument of a `#[jade::is_authorized]` function to let
+/// the macro fill it through the fetch host call, `C` is the type of the
+/// authorizer config which is decoded with the jam codec.
+#[derive(Debug)]
+pub struct AuthContext<C> {
+    /// The core index of the invocation
+    pub core: CoreIndex,
+
+    /// The authorization token of the work package
+    pub token: Vec<u8>,
+
+    /// The decoded authorizer config
+    pub config: C,
+
+    /// The work package to authorize
+    pub package: WorkPackage,
+}
+
+impl<C: DeserializeOwned> AuthContext<C> {
+    /// Fetch the context of the current invocation
+    pub fn fetch(core: CoreIndex) -> Result<Self> {
+        let token = fetch::auth_token()?;
+        let raw = fetch::auth_config()?;
+        let config = codec::decode(raw.as_slice())
+            .map_err(|e| anyhow!("failed to decode authorizer config: {e:?}"))?;
+        let package = fetch::package()?;
+
+        Ok(Self {
+            core,
+            token,
+            config,
+            package,
+        })
+    }
+}
@@ -11,16 +11,55 @@ pub fn gas() -> u64 {
 /// Fetch operations
 pub mod fetch {
     use super::*;
-    use crate::prelude::{Vec, vec};
-    use anyhow::Result;
+    use crate::prelude::{Vec, WorkPackage, vec};
+    use anyhow::{Result, anyhow};
     use service::vm::AccumulateItem;
 
-    /// Fetch a value from the storage
-    pub fn items() -> Result<Vec<AccumulateItem>> {
-        let len = unsafe { import::fetch(core::ptr::null_mut(), 0, 0, 14, 0, 0) };
+    /// The host call returns this value if the requested data is not available
+    const NONE: u64 = u64::MAX;
+
+    /// Fetch the raw data of the given kind
+    pub fn raw(kind: u64, a: u64, b: u64) -> Option<Vec<u8>> {
+        let len = unsafe { import::fetch(ptr::null_mut(), 0, 0, kind, a, b) };
+        if len == NONE {
+            return None;
+        }
+
         let mut target = vec![0; len as usize];
-        let _ = unsafe { import::fetch(target.as_mut_ptr(), 0, len as u64, 14, 0, 0) };
-        codec::decode(target.as_slice()).map_err(Into::into)
+        let _ = unsafe { import::fetch(target.as_mut_ptr(), 0, len, kind, a, b) };
+        Some(target)
+    }
+
+    /// Fetch an extrinsic of the refined work item
+    pub fn extrinsic(index: u64) -> Result<Vec<u8>> {
+        raw(4, index, 0).ok_or_else(|| anyhow!("extrinsic {index} not available"))
+    }
+
+    /// Fetch an extrinsic of a work item in the work package
+    pub fn item_extrinsic(item: u64, index: u64) -> Result<Vec<u8>> {
+        raw(3, item, index).ok_or_else(|| anyhow!("extrinsic {index} of item {item} not available"))
+    }
+
+    /// Fetch the work package
+    pub fn package() -> Result<WorkPackage> {
+        let encoded = raw(7, 0, 0).ok_or_else(|| anyhow!("work package not available"))?;
+        codec::decode(encoded.as_slice()).map_err(Into::into)
+    }
+
+    /// Fetch the authorizer config of the work package
+    pub fn auth_config() -> Result<Vec<u8>> {
+        raw(8, 0, 0).ok_or_else(|| anyhow!("authorizer config not available"))
+    }
+
+    /// Fetch the authorization token of the work package
+    pub fn auth_token() -> Result<Vec<u8>> {
+        raw(9, 0, 0).ok_or_else(|| anyhow!("authorization token not available"))
+    }
+
+    /// Fetch the accumulate items
+    pub fn items() -> Result<Vec<AccumulateItem>> {
+        let encoded = raw(14, 0, 0).ok_or_else(|| anyhow!("accumulate items not available"))?;
+        codec::decode(encoded.as_slice()).map_err(Into::into)
     }
 }
 
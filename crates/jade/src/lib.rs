@@ -7,6 +7,7 @@ extern crate alloc;
 
 pub use {codec, jade_derive::*, polkavm_derive, service};
 
+pub mod auth;
 pub mod host;
 pub mod logging;
 pub mod prelude;
@@ -1,5 +1,6 @@
 //! Re-export the prelude types
 
+pub use crate::auth::AuthContext;
 pub use codec;
 pub use service::{OpaqueHash, service::WorkPackage};
 
@@ -1,79 +1,290 @@
 //! Link spacevm to this sys library
+//!
+//! The library is resolved in the following order:
+//!
+//! 1. `SPACEVM_LIB_PATH`, the path of the library file
+//! 2. `SPACEVM_LIB_DIR`, a directory containing the library
+//! 3. `SPACEVM_LIB_TARBALL`, or the vendored `lib/<release>-<platform>.tar.gz`
+//! 4. the cached library in `lib/<platform>`
+//! 5. the release archive downloaded from GitHub
+//!
+//! Archives are verified against the pinned SHA-256 digests, or against
+//! `SPACEVM_LIB_SHA256` if it is set, the build fails on a mismatch or if
+//! there is no digest for the platform.
 
-use platforms::*;
 use std::{
     env, fs,
-    io::Result,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::Command,
 };
 
-const LIB_BASE: &str = "https://github.com/spacejamapp/specjam/releases/download/0.7.1-pre.1";
-const LIB_NAME: &str = "spacevm-0.7.1";
+/// The release of the prebuilt libraries
+///
+/// TODO: 0.7.2-pre.1 is the first release exporting [`SYMBOLS`], confirm it
+/// is published before pinning its digests.
+const LIB_BASE: &str = "https://github.com/spacejamapp/specjam/releases/download/0.7.2-pre.1";
+const LIB_NAME: &str = "spacevm-0.7.2";
 const PLATFORMS: [&str; 4] = ["linux-amd64", "linux-arm64", "macos-amd64", "macos-arm64"];
 
-fn main() -> std::io::Result<()> {
-    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
-    let libs = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("lib");
-    self::download_libs(&libs)?;
+/// Symbols required from the library besides the invocations
+const SYMBOLS: [&str; 2] = ["spacevm_free", "spacevm_last_error"];
+
+/// Pinned SHA-256 digests of the release archives
+///
+/// `SPACEVM_LIB_SHA256` overrides the pinned digest, archives of platforms
+/// without one are rejected.
+///
+/// TODO: pin the digests of the 0.7.2-pre.1 archives, the `Checksums` step
+/// of CI fails while an entry is `None`.
+const CHECKSUMS: [(&str, Option<&str>); 4] = [
+    ("linux-amd64", None),
+    ("linux-arm64", None),
+    ("macos-amd64", None),
+    ("macos-arm64", None),
+];
+
+type Result<T> = std::result::Result<T, String>;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // the pure backend does not link spacevm
+    if env::var_os("CARGO_FEATURE_PURE").is_some() {
+        return;
+    }
+
+    if let Err(e) = self::link() {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Resolve, copy and link the library
+fn link() -> Result<()> {
+    for var in [
+        "SPACEVM_LIB_PATH",
+        "SPACEVM_LIB_DIR",
+        "SPACEVM_LIB_TARBALL",
+        "SPACEVM_LIB_SHA256",
+        "DOWNLOAD_ALL_LIBS",
+    ] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+
+    let out = PathBuf::from(env::var("OUT_DIR").map_err(|e| format!("OUT_DIR: {e}"))?);
+    let lib = self::lib_name()?;
+    let src = self::resolve(lib)?;
+    self::check_symbols(&src)?;
 
     // copy the lib to out dir
-    let target = out.join(LIB);
-    let src = libs.join(PLATFORM).join(LIB);
-    fs::copy(src, target)?;
+    fs::copy(&src, out.join(lib))
+        .map_err(|e| format!("failed to copy {} to {}: {e}", src.display(), out.display()))?;
 
     // link the library
     println!("cargo:rustc-link-search=native={}", out.display());
     println!("cargo:rustc-link-lib=dylib=spacevm");
-    println!("cargo:rerun-if-changed=build.rs");
     Ok(())
 }
 
-fn download_libs(lib: &Path) -> Result<()> {
+/// Resolve the path of the library
+fn resolve(lib: &str) -> Result<PathBuf> {
+    if let Some(path) = env::var_os("SPACEVM_LIB_PATH") {
+        return self::existing(PathBuf::from(path), "SPACEVM_LIB_PATH");
+    }
+
+    if let Some(dir) = env::var_os("SPACEVM_LIB_DIR") {
+        return self::existing(PathBuf::from(dir).join(lib), "SPACEVM_LIB_DIR");
+    }
+
+    let platform = self::platform()?;
+    let libs = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("lib");
+    let target = libs.join(platform);
+    let tarball = env::var_os("SPACEVM_LIB_TARBALL")
+        .map(PathBuf::from)
+        .or_else(|| Some(libs.join(self::archive(platform))).filter(|path| path.exists()));
+    if let Some(tarball) = tarball {
+        self::extract(&tarball, &target, platform)?;
+        return self::existing(target.join(lib), "SPACEVM_LIB_TARBALL");
+    }
+
     let dall = env::var("DOWNLOAD_ALL_LIBS")
         .map(|b| b == "true")
         .unwrap_or(false);
-    for platform in PLATFORMS {
-        let target = lib.join(platform);
-        if target.join(LIB).exists() || (!dall && platform != PLATFORM) {
-            continue;
+    for other in PLATFORMS {
+        if (dall || other == platform) && !libs.join(other).join(lib).exists() {
+            self::download(&libs.join(other), other)?;
         }
+    }
+
+    self::existing(target.join(lib), "the release archive")
+}
+
+/// Check that the library exports the required symbols
+///
+/// The symbol names are looked up in the raw bytes of the library, which
+/// contain the dynamic string table of both ELF and Mach-O files.
+fn check_symbols(lib: &Path) -> Result<()> {
+    let bytes = fs::read(lib).map_err(|e| format!("failed to read {}: {e}", lib.display()))?;
+    let missing = SYMBOLS
+        .into_iter()
+        .filter(|symbol| {
+            let name = format!("{symbol}\0");
+            !bytes
+                .windows(name.len())
+                .any(|window| window == name.as_bytes())
+        })
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
 
-        fs::create_dir_all(&target)?;
-        let mut curl = Command::new("curl");
-        curl.current_dir(&target);
-        curl.stdout(Stdio::piped());
-        curl.arg("-L");
-        curl.arg(format!("{LIB_BASE}/{LIB_NAME}-{platform}.tar.gz"));
-        let output = curl.spawn()?;
-
-        let mut tar = Command::new("tar");
-        tar.current_dir(&target);
-        tar.stdin(Stdio::from(
-            output.stdout.expect("failed to download library"),
+    Err(format!(
+        "{} does not export {}, {LIB_NAME} or newer is required",
+        lib.display(),
+        missing.join(", ")
+    ))
+}
+
+/// Download and extract the release archive of a platform
+fn download(target: &Path, platform: &str) -> Result<()> {
+    fs::create_dir_all(target)
+        .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+
+    let archive = target.join(self::archive(platform));
+    let url = format!("{LIB_BASE}/{}", self::archive(platform));
+    self::run(
+        Command::new("curl")
+            .args(["-fsSL", "-o"])
+            .arg(&archive)
+            .arg(&url),
+        &format!(
+            "failed to download {url}, set SPACEVM_LIB_PATH or SPACEVM_LIB_TARBALL to build offline"
+        ),
+    )?;
+
+    let result = self::extract(&archive, target, platform);
+    let _ = fs::remove_file(&archive);
+    result
+}
+
+/// Verify and extract an archive
+fn extract(archive: &Path, target: &Path, platform: &str) -> Result<()> {
+    self::verify(archive, platform)?;
+    fs::create_dir_all(target)
+        .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+    self::run(
+        Command::new("tar")
+            .arg("xzf")
+            .arg(archive)
+            .arg("-C")
+            .arg(target),
+        &format!("failed to extract {}", archive.display()),
+    )
+}
+
+/// Verify the SHA-256 digest of an archive
+fn verify(archive: &Path, platform: &str) -> Result<()> {
+    let expected = env::var("SPACEVM_LIB_SHA256").ok().or_else(|| {
+        CHECKSUMS
+            .iter()
+            .find(|(name, _)| *name == platform)
+            .and_then(|(_, digest)| digest.map(Into::into))
+    });
+
+    let Some(expected) = expected else {
+        return Err(format!(
+            "no pinned SHA-256 digest of {LIB_NAME} for {platform}, set SPACEVM_LIB_SHA256 \
+             to verify {}",
+            archive.display()
+        ));
+    };
+
+    let actual = self::sha256(archive)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!(
+            "SHA-256 mismatch of {}: expected {expected}, got {actual}",
+            archive.display()
         ));
-        tar.args(["xvzf", "-"]);
-        tar.status()?;
     }
 
     Ok(())
 }
 
-mod platforms {
-    #[cfg(target_os = "linux")]
-    pub const LIB: &str = "libspacevm.so";
-    #[cfg(target_os = "macos")]
-    pub const LIB: &str = "libspacevm.dylib";
+/// Compute the SHA-256 digest of a file
+fn sha256(path: &Path) -> Result<String> {
+    let output = Command::new("sha256sum")
+        .arg(path)
+        .output()
+        .or_else(|_| {
+            Command::new("shasum")
+                .args(["-a", "256"])
+                .arg(path)
+                .output()
+        })
+        .map_err(|e| format!("neither sha256sum nor shasum is available: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("failed to hash {}", path.display()));
+    }
 
-    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-    pub const PLATFORM: &str = "linux-amd64";
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(Into::into)
+        .ok_or_else(|| format!("failed to hash {}", path.display()))
+}
 
-    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-    pub const PLATFORM: &str = "linux-arm64";
+/// Run a command and check its exit status
+fn run(command: &mut Command, error: &str) -> Result<()> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{error} ({status})")),
+        Err(e) => Err(format!("{error} ({e})")),
+    }
+}
+
+/// Check that the resolved library exists
+fn existing(path: PathBuf, source: &str) -> Result<PathBuf> {
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!(
+            "spacevm library not found at {} (resolved from {source})",
+            path.display()
+        ))
+    }
+}
+
+/// The name of the release archive of a platform
+fn archive(platform: &str) -> String {
+    format!("{LIB_NAME}-{platform}.tar.gz")
+}
+
+/// The file name of the library on the target platform
+fn lib_name() -> Result<&'static str> {
+    match env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        "macos" => Ok("libspacevm.dylib"),
+        "windows" => Err(self::unsupported("windows")),
+        _ => Ok("libspacevm.so"),
+    }
+}
 
-    #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-    pub const PLATFORM: &str = "macos-amd64";
+/// The release platform of the target
+fn platform() -> Result<&'static str> {
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    match (os.as_str(), arch.as_str()) {
+        ("linux", "x86_64") => Ok("linux-amd64"),
+        ("linux", "aarch64") => Ok("linux-arm64"),
+        ("macos", "x86_64") => Ok("macos-amd64"),
+        ("macos", "aarch64") => Ok("macos-arm64"),
+        _ => Err(self::unsupported(&format!("{os}-{arch}"))),
+    }
+}
 
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    pub const PLATFORM: &str = "macos-arm64";
+/// The error of an unsupported platform
+fn unsupported(platform: &str) -> String {
+    format!(
+        "spacevm has no prebuilt library for {platform}, set SPACEVM_LIB_PATH or \
+         SPACEVM_LIB_DIR to a local build, or enable the `pure` feature of spacevm-sys"
+    )
 }
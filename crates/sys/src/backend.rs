@@ -0,0 +1,92 @@
+//! Runtime selection of the execution backend
+
+use anyhow::{Error, anyhow};
+use core::{fmt, str::FromStr};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The environment variable of the global backend
+const BACKEND_ENV: &str = "SPACEVM_BACKEND";
+
+/// The global backend is not resolved yet
+const UNSET: u8 = u8::MAX;
+
+/// The global backend
+static GLOBAL: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Execution backend of spacevm
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The PVM interpreter
+    Interpreter = 0,
+
+    /// The PVM recompiler
+    Compiler = 1,
+}
+
+impl Backend {
+    /// The backend selected by the cargo features
+    ///
+    /// The `pure` backend only provides the interpreter.
+    pub const DEFAULT: Self = if cfg!(any(feature = "interp", feature = "pure")) {
+        Self::Interpreter
+    } else {
+        Self::Compiler
+    };
+
+    /// Get the global backend
+    ///
+    /// Resolved from `SPACEVM_BACKEND` on the first call, falls back to
+    /// [`Backend::DEFAULT`].
+    pub fn global() -> Self {
+        match GLOBAL.load(Ordering::Relaxed) {
+            0 => Self::Interpreter,
+            1 => Self::Compiler,
+            _ => {
+                let backend = std::env::var(BACKEND_ENV)
+                    .ok()
+                    .and_then(|name| name.parse().ok())
+                    .unwrap_or(Self::DEFAULT);
+                let _ = GLOBAL.compare_exchange(
+                    UNSET,
+                    backend as u8,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                );
+                Self::global()
+            }
+        }
+    }
+
+    /// Set the global backend
+    pub fn set_global(self) {
+        GLOBAL.store(self as u8, Ordering::Relaxed);
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::global()
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "interp" | "interpreter" => Ok(Self::Interpreter),
+            "comp" | "compiler" => Ok(Self::Compiler),
+            _ => Err(anyhow!("unknown spacevm backend: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Interpreter => f.write_str("interpreter"),
+            Self::Compiler => f.write_str("compiler"),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Protocol constants exposed to the services
+//!
+//! The constants are configured per thread, and default to the preset
+//! selected by the `tiny` feature. Only the pure backend reads the configured
+//! constants.
+
+use std::cell::Cell;
+
+thread_local! {
+    /// The constants configured on the current thread
+    static CONSTANTS: Cell<Option<Constants>> = const { Cell::new(None) };
+}
+
+/// Configure the constants of the invocations on the current thread
+pub fn configure(constants: Constants) {
+    CONSTANTS.with(|current| current.set(Some(constants)));
+}
+
+/// Reset the constants of the current thread to the preset
+pub fn reset() {
+    CONSTANTS.with(|current| current.set(None));
+}
+
+/// Protocol constants of the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constants {
+    /// The additional minimum balance per item of elective service state
+    pub item_deposit: u64,
+
+    /// The additional minimum balance per octet of elective service state
+    pub byte_deposit: u64,
+
+    /// The basic minimum balance which all services require
+    pub base_deposit: u64,
+
+    /// The total number of cores
+    pub cores: u16,
+
+    /// The period in timeslots after which an unreferenced preimage may be expunged
+    pub expunge_period: u32,
+
+    /// The length of an epoch in timeslots
+    pub epoch_length: u32,
+
+    /// The gas allocated to invoke a work report's accumulation logic
+    pub accumulate_gas: u64,
+
+    /// The gas allocated to invoke a work package's is-authorized logic
+    pub authorize_gas: u64,
+
+    /// The gas allocated to invoke a work package's refine logic
+    pub refine_gas: u64,
+
+    /// The total gas allocated across all accumulation
+    pub total_accumulate_gas: u64,
+
+    /// The size of recent history in blocks
+    pub recent_history: u16,
+
+    /// The maximum amount of work items in a package
+    pub max_items: u16,
+
+    /// The maximum number of dependency items in a work report
+    pub max_dependencies: u16,
+
+    /// The maximum number of tickets which may be submitted in a single extrinsic
+    pub max_tickets: u16,
+
+    /// The maximum age in timeslots of the lookup anchor
+    pub max_lookup_age: u32,
+
+    /// The number of ticket entries per validator
+    pub ticket_entries: u16,
+
+    /// The maximum number of items in the authorizers pool
+    pub auth_pool: u16,
+
+    /// The period of a timeslot in seconds
+    pub slot_period: u16,
+
+    /// The number of items in the authorizers queue
+    pub auth_queue: u16,
+
+    /// The rotation period of validator-core assignments
+    pub rotation_period: u16,
+
+    /// The maximum number of extrinsics in a work package
+    pub max_extrinsics: u16,
+
+    /// The period in timeslots after which reported but unavailable work may be replaced
+    pub report_timeout: u16,
+
+    /// The total number of validators
+    pub validators: u16,
+
+    /// The maximum size of is-authorized code in octets
+    pub max_auth_code: u32,
+
+    /// The maximum size of an encoded work package with extrinsic data and imports
+    pub max_bundle: u32,
+
+    /// The maximum size of service code in octets
+    pub max_service_code: u32,
+
+    /// The basic size of erasure-coded pieces in octets
+    pub piece_size: u32,
+
+    /// The maximum number of imports in a work package
+    pub max_imports: u32,
+
+    /// The number of erasure-coded pieces in a segment
+    pub segment_pieces: u32,
+
+    /// The maximum total size of all unbounded blobs in a work report
+    pub max_report_blobs: u32,
+
+    /// The size of a transfer memo in octets
+    pub memo_size: u32,
+
+    /// The maximum number of exports in a work package
+    pub max_exports: u32,
+
+    /// The number of slots into an epoch at which ticket-submission ends
+    pub ticket_tail: u32,
+}
+
+impl Constants {
+    /// The constants of the full chain
+    pub const fn full() -> Self {
+        Self {
+            item_deposit: 10,
+            byte_deposit: 1,
+            base_deposit: 100,
+            cores: 341,
+            expunge_period: 19_200,
+            epoch_length: 600,
+            accumulate_gas: 10_000_000,
+            authorize_gas: 50_000_000,
+            refine_gas: 5_000_000_000,
+            total_accumulate_gas: 3_500_000_000,
+            recent_history: 8,
+            max_items: 16,
+            max_dependencies: 8,
+            max_tickets: 16,
+            max_lookup_age: 14_400,
+            ticket_entries: 2,
+            auth_pool: 8,
+            slot_period: 6,
+            auth_queue: 80,
+            rotation_period: 10,
+            max_extrinsics: 128,
+            report_timeout: 5,
+            validators: 1023,
+            max_auth_code: 64_000,
+            max_bundle: 13_794_305,
+            max_service_code: 4_000_000,
+            piece_size: 684,
+            max_imports: 3072,
+            segment_pieces: 6,
+            max_report_blobs: 48 * 1024,
+            memo_size: 128,
+            max_exports: 3072,
+            ticket_tail: 500,
+        }
+    }
+
+    /// The constants of the tiny chain
+    pub const fn tiny() -> Self {
+        Self {
+            cores: 2,
+            expunge_period: 32,
+            epoch_length: 12,
+            refine_gas: 1_000_000_000,
+            total_accumulate_gas: 20_000_000,
+            max_tickets: 3,
+            ticket_entries: 3,
+            rotation_period: 4,
+            validators: 6,
+            piece_size: 4,
+            segment_pieces: 1026,
+            ticket_tail: 10,
+            ..Self::full()
+        }
+    }
+
+    /// The constants of the chain selected by the `tiny` feature
+    pub const fn preset() -> Self {
+        if cfg!(feature = "tiny") {
+            Self::tiny()
+        } else {
+            Self::full()
+        }
+    }
+
+    /// The size of an exported segment in octets
+    pub const fn segment_size(&self) -> u32 {
+        self.piece_size * self.segment_pieces
+    }
+
+    /// Encode the constants in the order of the fetch host call
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(134);
+        out.extend_from_slice(&self.item_deposit.to_le_bytes());
+        out.extend_from_slice(&self.byte_deposit.to_le_bytes());
+        out.extend_from_slice(&self.base_deposit.to_le_bytes());
+        out.extend_from_slice(&self.cores.to_le_bytes());
+        out.extend_from_slice(&self.expunge_period.to_le_bytes());
+        out.extend_from_slice(&self.epoch_length.to_le_bytes());
+        out.extend_from_slice(&self.accumulate_gas.to_le_bytes());
+        out.extend_from_slice(&self.authorize_gas.to_le_bytes());
+        out.extend_from_slice(&self.refine_gas.to_le_bytes());
+        out.extend_from_slice(&self.total_accumulate_gas.to_le_bytes());
+        out.extend_from_slice(&self.recent_history.to_le_bytes());
+        out.extend_from_slice(&self.max_items.to_le_bytes());
+        out.extend_from_slice(&self.max_dependencies.to_le_bytes());
+        out.extend_from_slice(&self.max_tickets.to_le_bytes());
+        out.extend_from_slice(&self.max_lookup_age.to_le_bytes());
+        out.extend_from_slice(&self.ticket_entries.to_le_bytes());
+        out.extend_from_slice(&self.auth_pool.to_le_bytes());
+        out.extend_from_slice(&self.slot_period.to_le_bytes());
+        out.extend_from_slice(&self.auth_queue.to_le_bytes());
+        out.extend_from_slice(&self.rotation_period.to_le_bytes());
+        out.extend_from_slice(&self.max_extrinsics.to_le_bytes());
+        out.extend_from_slice(&self.report_timeout.to_le_bytes());
+        out.extend_from_slice(&self.validators.to_le_bytes());
+        out.extend_from_slice(&self.max_auth_code.to_le_bytes());
+        out.extend_from_slice(&self.max_bundle.to_le_bytes());
+        out.extend_from_slice(&self.max_service_code.to_le_bytes());
+        out.extend_from_slice(&self.piece_size.to_le_bytes());
+        out.extend_from_slice(&self.max_imports.to_le_bytes());
+        out.extend_from_slice(&self.segment_pieces.to_le_bytes());
+        out.extend_from_slice(&self.max_report_blobs.to_le_bytes());
+        out.extend_from_slice(&self.memo_size.to_le_bytes());
+        out.extend_from_slice(&self.max_exports.to_le_bytes());
+        out.extend_from_slice(&self.ticket_tail.to_le_bytes());
+        out
+    }
+}
+
+impl Default for Constants {
+    fn default() -> Self {
+        CONSTANTS.with(Cell::get).unwrap_or(Self::preset())
+    }
+}
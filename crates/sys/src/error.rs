@@ -0,0 +1,63 @@
+//! Errors of the spacevm invocations
+
+use core::fmt;
+
+/// Error of a spacevm invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The native library reported a failure
+    Native {
+        /// The called function
+        call: &'static str,
+
+        /// The message of the native library
+        message: String,
+    },
+
+    /// The native library returned no output
+    NoOutput {
+        /// The called function
+        call: &'static str,
+    },
+
+    /// The output of the native library could not be decoded
+    Decode {
+        /// The called function
+        call: &'static str,
+
+        /// The length of the output
+        len: usize,
+
+        /// The decoding error
+        message: String,
+    },
+
+    /// The invocation is not supported by the backend
+    Unsupported {
+        /// The called function
+        call: &'static str,
+
+        /// The unsupported feature
+        feature: &'static str,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Native { call, message } => write!(f, "{call} failed: {message}"),
+            Self::NoOutput { call } => write!(f, "{call} returned no output"),
+            Self::Decode { call, len, message } => {
+                write!(
+                    f,
+                    "failed to decode the {len} bytes output of {call}: {message}"
+                )
+            }
+            Self::Unsupported { call, feature } => {
+                write!(f, "{call} does not support {feature} on this backend")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,70 @@
+//! Guest logs of the invocations
+//!
+//! Logs are captured per thread between [`start`] and [`stop`], they are
+//! only captured by the pure backend.
+
+use core::fmt;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The captured logs of the current thread
+    static LOGS: RefCell<Option<Vec<Log>>> = const { RefCell::new(None) };
+}
+
+/// A log emitted by the guest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    /// The logging service
+    pub service: u32,
+
+    /// The level, from 0 for errors to 4 for traces
+    pub level: u64,
+
+    /// The target of the log
+    pub target: String,
+
+    /// The message
+    pub message: String,
+}
+
+impl fmt::Display for Log {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            0 => "ERROR",
+            1 => "WARN",
+            2 => "INFO",
+            3 => "DEBUG",
+            _ => "TRACE",
+        };
+        write!(f, "{level} service={}", self.service)?;
+        if !self.target.is_empty() {
+            write!(f, " {}", self.target)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Start capturing the logs of the current thread
+pub fn start() {
+    LOGS.with(|logs| *logs.borrow_mut() = Some(Vec::new()));
+}
+
+/// Stop capturing, returns the captured logs
+pub fn stop() -> Vec<Log> {
+    LOGS.with(|logs| logs.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+/// If the logs are captured on the current thread
+pub fn enabled() -> bool {
+    LOGS.with(|logs| logs.borrow().is_some())
+}
+
+/// Record a log if capturing
+pub fn record(log: Log) {
+    LOGS.with(|logs| {
+        if let Some(logs) = logs.borrow_mut().as_mut() {
+            logs.push(log);
+        }
+    });
+}
@@ -0,0 +1,234 @@
+//! Bindings of the native spacevm library
+
+use crate::{Backend, Error, native::abi::Buffer};
+pub use abi::init_logger;
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+use service::{
+    api::{AccumulateArgs, Accumulated, AuthorizeArgs, RefineArgs},
+    service::result::{Executed, Refined},
+};
+
+/// Run the authorize invocation
+pub fn authorize(args: AuthorizeArgs) -> Result<Executed> {
+    self::authorize_with(Backend::global(), args)
+}
+
+/// Run the refine invocation
+pub fn refine(args: RefineArgs) -> Result<Refined> {
+    self::refine_with(Backend::global(), args)
+}
+
+/// Run the accumulate invocation
+pub fn accumulate(args: AccumulateArgs) -> Result<Accumulated> {
+    self::accumulate_with(Backend::global(), args)
+}
+
+/// Run the authorize invocation with the given backend
+pub fn authorize_with(backend: Backend, args: AuthorizeArgs) -> Result<Executed> {
+    match backend {
+        Backend::Interpreter => self::invoke(("interp_authorize", abi::interp_authorize), &args),
+        Backend::Compiler => self::invoke(("comp_authorize", abi::comp_authorize), &args),
+    }
+}
+
+/// Run the refine invocation with the given backend
+pub fn refine_with(backend: Backend, args: RefineArgs) -> Result<Refined> {
+    match backend {
+        Backend::Interpreter => self::invoke(("interp_refine", abi::interp_refine), &args),
+        Backend::Compiler => self::invoke(("comp_refine", abi::comp_refine), &args),
+    }
+}
+
+/// Run the accumulate invocation with the given backend
+pub fn accumulate_with(backend: Backend, args: AccumulateArgs) -> Result<Accumulated> {
+    match backend {
+        Backend::Interpreter => self::invoke(("interp_accumulate", abi::interp_accumulate), &args),
+        Backend::Compiler => self::invoke(("comp_accumulate", abi::comp_accumulate), &args),
+    }
+}
+
+/// Call an invocation of the native library
+fn invoke<A: Serialize, R: DeserializeOwned>(
+    (name, call): (&'static str, unsafe extern "C" fn(Buffer) -> Buffer),
+    args: &A,
+) -> Result<R> {
+    let encoded = codec::encode(args)?;
+    let input = Buffer {
+        ptr: encoded.as_ptr(),
+        len: encoded.len(),
+    };
+
+    // the input stays owned by us, the output is owned by the library
+    let output = Output::new(unsafe { call(input) });
+    self::decode(name, &output)
+}
+
+/// Decode an output of the native library
+///
+/// A null output is a failure, reported by `spacevm_last_error`.
+fn decode<R: DeserializeOwned>(name: &'static str, output: &Output) -> Result<R> {
+    let Some(bytes) = output.as_slice() else {
+        let error = Output::new(unsafe { abi::spacevm_last_error() });
+        return Err(match error.as_slice() {
+            Some(message) => Error::Native {
+                call: name,
+                message: String::from_utf8_lossy(message).into(),
+            },
+            None => Error::NoOutput { call: name },
+        }
+        .into());
+    };
+
+    codec::decode(bytes).map_err(|e| {
+        Error::Decode {
+            call: name,
+            len: bytes.len(),
+            message: e.to_string(),
+        }
+        .into()
+    })
+}
+
+/// An output buffer allocated by the native library
+///
+/// Released with `spacevm_free` on drop.
+struct Output {
+    buffer: Buffer,
+    release: unsafe extern "C" fn(Buffer),
+}
+
+impl Output {
+    /// Wrap an output of the native library
+    fn new(buffer: Buffer) -> Self {
+        Self {
+            buffer,
+            release: abi::spacevm_free,
+        }
+    }
+
+    /// Get the output as a byte slice, `None` if the library returned null
+    fn as_slice(&self) -> Option<&[u8]> {
+        if self.buffer.ptr.is_null() {
+            None
+        } else if self.buffer.len == 0 {
+            Some(&[])
+        } else {
+            Some(unsafe { core::slice::from_raw_parts(self.buffer.ptr, self.buffer.len) })
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.buffer.ptr.is_null() {
+            unsafe { (self.release)(self.buffer) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        ptr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// The count of the released test outputs
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Count a released test output
+    unsafe extern "C" fn release(_buffer: Buffer) {
+        RELEASED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An invocation returning null
+    unsafe extern "C" fn null(_args: Buffer) -> Buffer {
+        Buffer {
+            ptr: ptr::null(),
+            len: 0,
+        }
+    }
+
+    #[test]
+    fn report_a_null_output() {
+        // no call failed on this thread, so there is no error message
+        let error = super::invoke::<_, Executed>(("null", self::null), &0u8).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Error>(),
+            Some(&Error::NoOutput { call: "null" })
+        );
+    }
+
+    #[test]
+    fn decode_a_zero_length_output() {
+        let empty = [0u8; 0];
+        let output = Output {
+            buffer: Buffer {
+                ptr: empty.as_ptr(),
+                len: 0,
+            },
+            release: self::release,
+        };
+
+        let error = super::decode::<Executed>("empty", &output).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::Decode { len: 0, .. })
+        ));
+        drop(output);
+        assert_eq!(RELEASED.load(Ordering::Relaxed), 1);
+
+        // null outputs are not released
+        drop(Output {
+            buffer: Buffer {
+                ptr: ptr::null(),
+                len: 0,
+            },
+            release: self::release,
+        });
+        assert_eq!(RELEASED.load(Ordering::Relaxed), 1);
+    }
+}
+
+mod abi {
+    /// A byte buffer passed across the FFI boundary
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct Buffer {
+        pub ptr: *const u8,
+        pub len: usize,
+    }
+
+    unsafe extern "C" {
+        /// Initialize the logger
+        pub fn init_logger(ansi: bool, timer: bool);
+
+        /// Release a buffer returned by the library
+        pub fn spacevm_free(buffer: Buffer);
+
+        /// Get the error message of the last failed call on this thread
+        ///
+        /// Returns a null buffer if there is no error.
+        pub fn spacevm_last_error() -> Buffer;
+
+        /// Run the authorize invocation
+        pub fn comp_authorize(args: Buffer) -> Buffer;
+
+        /// Run the refine invocation
+        pub fn comp_refine(args: Buffer) -> Buffer;
+
+        /// Run the accumulate invocation
+        pub fn comp_accumulate(args: Buffer) -> Buffer;
+
+        /// Run the is_authorized invocation
+        pub fn interp_authorize(args: Buffer) -> Buffer;
+
+        /// Run the refine invocation
+        pub fn interp_refine(args: Buffer) -> Buffer;
+
+        /// Run accumulate invocation
+        pub fn interp_accumulate(args: Buffer) -> Buffer;
+    }
+}
@@ -0,0 +1,592 @@
+//! Gas profiling of the invocations
+//!
+//! Profiling is configured per thread like [`crate::trace`], the gas of
+//! every step is attributed to the current call stack of the guest and to
+//! the host calls. Functions are named with the exports and the debug info
+//! of the `.pvm` blob, or with their entry program counter. Steps are only
+//! profiled by the pure backend.
+
+use crate::trace;
+use anyhow::{Result, anyhow, bail};
+use core::fmt;
+use polkavm_common::program::{ISA32_V1, ISA64_V1, InstructionSet, ProgramBlob};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
+
+thread_local! {
+    /// The profiler of the current thread
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// The magic bytes of the `.pvm` blob
+const PVM_MAGIC: [u8; 4] = *b"PVM\0";
+
+/// The section of the exports in the `.pvm` blob
+const SECTION_EXPORTS: u8 = 5;
+
+/// The end of the sections in the `.pvm` blob
+const SECTION_END_OF_FILE: u8 = 0;
+
+/// The prefix of the host call frames
+const HOST_PREFIX: &str = "host:";
+
+/// Function symbols by their entry program counter
+#[derive(Debug, Clone, Default)]
+pub struct Symbols(BTreeMap<u32, String>);
+
+impl Symbols {
+    /// Load the symbols of a `.pvm` blob file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let blob =
+            std::fs::read(path).map_err(|e| anyhow!("failed to read {}: {e}", path.display()))?;
+        Self::from_pvm(&blob)
+    }
+
+    /// Parse the symbols of a `.pvm` blob
+    ///
+    /// The functions are named with the debug info, which the linker fills
+    /// from the ELF symbol table unless the blob is stripped, and the entry
+    /// points with the exports.
+    pub fn from_pvm(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader(blob);
+        if reader.take(PVM_MAGIC.len())? != PVM_MAGIC {
+            bail!("invalid pvm blob magic");
+        }
+
+        // skip the version and the blob length
+        reader.take(9)?;
+        let mut symbols = Self::from_debug_info(blob)?;
+        while !reader.0.is_empty() {
+            let section = reader.take(1)?[0];
+            if section == SECTION_END_OF_FILE {
+                break;
+            }
+
+            let len = reader.varint()? as usize;
+            let mut data = Reader(reader.take(len)?);
+            if section != SECTION_EXPORTS {
+                continue;
+            }
+
+            for _ in 0..data.varint()? {
+                let pc = data.varint()?;
+                let len = data.varint()? as usize;
+                let name = String::from_utf8_lossy(data.take(len)?);
+                symbols.insert(pc, name);
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    /// Parse the function names of the debug info of a `.pvm` blob
+    pub fn from_debug_info(blob: &[u8]) -> Result<Self> {
+        let blob = ProgramBlob::parse(blob.to_vec().into())
+            .map_err(|e| anyhow!("invalid pvm blob: {e}"))?;
+
+        let mut symbols = Self::default();
+        if blob.is_64_bit() {
+            symbols.functions(&blob, ISA64_V1)?;
+        } else {
+            symbols.functions(&blob, ISA32_V1)?;
+        }
+
+        Ok(symbols)
+    }
+
+    /// Name the first instruction of every function range of the debug info
+    fn functions(&mut self, blob: &ProgramBlob, isa: impl InstructionSet) -> Result<()> {
+        let mut range = None;
+        for instruction in blob.instructions(isa) {
+            let pc = instruction.offset;
+            let Some(mut program) = blob
+                .get_debug_line_program_at(pc)
+                .map_err(|e| anyhow!("invalid debug info at {}: {e}", pc.0))?
+            else {
+                continue;
+            };
+
+            if range.replace(program.entry_index()) == Some(program.entry_index()) {
+                continue;
+            }
+
+            let Some(region) = program
+                .run()
+                .map_err(|e| anyhow!("invalid line program at {}: {e}", pc.0))?
+            else {
+                continue;
+            };
+
+            if let Some(frame) = region.frames().next() {
+                let name = frame
+                    .full_name()
+                    .map_err(|e| anyhow!("invalid function name at {}: {e}", pc.0))?
+                    .to_string();
+                if !name.is_empty() {
+                    self.0.entry(pc.0).or_insert(name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Name the function at a program counter
+    pub fn insert(&mut self, pc: u32, name: impl Into<String>) {
+        self.0.insert(pc, name.into());
+    }
+
+    /// Get the name of the function at a program counter
+    pub fn get(&self, pc: u32) -> Option<&str> {
+        self.0.get(&pc).map(String::as_str)
+    }
+
+    /// Get the name of a frame
+    fn frame(&self, pc: u32) -> String {
+        self.get(pc)
+            .map(Into::into)
+            .unwrap_or_else(|| format!("{pc:#x}"))
+    }
+}
+
+/// The gas usage of a function or host call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// The number of calls
+    pub calls: u64,
+
+    /// The gas used by the frame itself
+    pub self_gas: u64,
+
+    /// The gas used by the frame and its callees
+    pub total_gas: u64,
+}
+
+/// A gas profile of invocations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// The gas used by the folded call stacks
+    pub stacks: BTreeMap<String, u64>,
+
+    /// The number of calls of the frames
+    pub calls: BTreeMap<String, u64>,
+}
+
+impl Profile {
+    /// The total gas of the profile
+    pub fn total(&self) -> u64 {
+        self.stacks.values().sum()
+    }
+
+    /// Merge another profile into this one
+    pub fn merge(&mut self, other: Profile) {
+        for (stack, gas) in other.stacks {
+            *self.stacks.entry(stack).or_default() += gas;
+        }
+
+        for (frame, calls) in other.calls {
+            *self.calls.entry(frame).or_default() += calls;
+        }
+    }
+
+    /// The gas usage of the frames, sorted by the total gas
+    pub fn usage(&self) -> Vec<(String, Usage)> {
+        let mut usage = BTreeMap::<&str, Usage>::new();
+        for (stack, gas) in &self.stacks {
+            let frames = stack.split(';').collect::<Vec<_>>();
+            let leaf = frames.last().copied().unwrap_or_default();
+            usage.entry(leaf).or_default().self_gas += gas;
+            for frame in frames.into_iter().collect::<BTreeSet<_>>() {
+                usage.entry(frame).or_default().total_gas += gas;
+            }
+        }
+
+        for (frame, calls) in &self.calls {
+            usage.entry(frame).or_default().calls += calls;
+        }
+
+        let mut usage = usage
+            .into_iter()
+            .map(|(frame, usage)| (frame.to_string(), usage))
+            .collect::<Vec<_>>();
+        usage.sort_by_key(|(_, usage)| core::cmp::Reverse(usage.total_gas));
+        usage
+    }
+
+    /// The gas usage of the host calls
+    pub fn host_calls(&self) -> Vec<(String, Usage)> {
+        self.usage()
+            .into_iter()
+            .filter(|(frame, _)| frame.starts_with(HOST_PREFIX))
+            .collect()
+    }
+
+    /// The gas usage of the guest functions
+    pub fn functions(&self) -> Vec<(String, Usage)> {
+        self.usage()
+            .into_iter()
+            .filter(|(frame, _)| !frame.starts_with(HOST_PREFIX))
+            .collect()
+    }
+
+    /// Render the folded stacks, the input format of flamegraph tools
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, gas)| format!("{stack} {gas}\n"))
+            .collect()
+    }
+
+    /// Write the folded stacks to a file
+    pub fn write_folded(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.folded())
+            .map_err(|e| anyhow!("failed to write {}: {e}", path.display()))
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().max(1);
+        writeln!(
+            f,
+            "{:<40} {:>8} {:>12} {:>12} {:>7}",
+            "frame", "calls", "self", "total", "%"
+        )?;
+        for (frame, usage) in self.usage() {
+            writeln!(
+                f,
+                "{:<40} {:>8} {:>12} {:>12} {:>6.2}%",
+                frame,
+                usage.calls,
+                usage.self_gas,
+                usage.total_gas,
+                usage.total_gas as f64 * 100.0 / total as f64
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The control flow of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Continue in the current function
+    Next,
+
+    /// Call the function at `entry`, which returns to `ret`
+    Call {
+        /// The entry of the callee
+        entry: u32,
+
+        /// The return address of the caller
+        ret: u32,
+    },
+
+    /// Jump indirectly to a program counter, returns if it is the return
+    /// address of a frame
+    Jump(u32),
+}
+
+/// A frame of the call stack
+struct Frame {
+    /// The folded stack up to this frame
+    stack: String,
+
+    /// The return address of the caller
+    ret: u32,
+}
+
+/// Profiler of the current thread
+struct Profiler {
+    /// The function symbols
+    symbols: Symbols,
+
+    /// The call stack of the invocation
+    frames: Vec<Frame>,
+
+    /// The gas used by the folded stacks
+    stacks: HashMap<String, u64>,
+
+    /// The number of calls of the frames
+    calls: BTreeMap<String, u64>,
+}
+
+impl Profiler {
+    /// Charge gas to a folded stack
+    fn charge(&mut self, stack: &str, gas: u64) {
+        match self.stacks.get_mut(stack) {
+            Some(used) => *used += gas,
+            None => {
+                self.stacks.insert(stack.into(), gas);
+            }
+        }
+    }
+
+    /// Count a call of a frame
+    fn call(&mut self, frame: String) {
+        *self.calls.entry(frame).or_default() += 1;
+    }
+}
+
+/// Start profiling on the current thread
+pub fn start(symbols: Symbols) {
+    PROFILER.with_borrow_mut(|profiler| {
+        *profiler = Some(Profiler {
+            symbols,
+            frames: Vec::new(),
+            stacks: HashMap::new(),
+            calls: BTreeMap::new(),
+        });
+    });
+}
+
+/// Stop profiling on the current thread, returns the profile
+pub fn stop() -> Profile {
+    PROFILER
+        .with_borrow_mut(Option::take)
+        .map(|profiler| Profile {
+            stacks: profiler.stacks.into_iter().collect(),
+            calls: profiler.calls,
+        })
+        .unwrap_or_default()
+}
+
+/// If profiling is enabled on the current thread
+pub fn enabled() -> bool {
+    PROFILER.with_borrow(Option::is_some)
+}
+
+/// Enter an invocation, the root frame is named after it
+pub fn enter(invocation: &str) {
+    PROFILER.with_borrow_mut(|profiler| {
+        let Some(profiler) = profiler else {
+            return;
+        };
+
+        profiler.frames = vec![Frame {
+            stack: invocation.into(),
+            ret: u32::MAX,
+        }];
+        profiler.call(invocation.into());
+    });
+}
+
+/// Record the gas of a step and its control flow
+pub fn record(gas: u64, host: Option<u32>, flow: Flow) {
+    PROFILER.with_borrow_mut(|profiler| {
+        let Some(profiler) = profiler else {
+            return;
+        };
+
+        let Some(frame) = profiler.frames.last() else {
+            return;
+        };
+
+        let base = frame.stack.clone();
+        match host {
+            Some(id) => {
+                let name = format!("{HOST_PREFIX}{}", trace::host_name(id));
+                profiler.charge(&format!("{base};{name}"), gas);
+                profiler.call(name);
+            }
+            None => profiler.charge(&base, gas),
+        }
+
+        match flow {
+            Flow::Next => {}
+            Flow::Call { entry, ret } => {
+                let name = profiler.symbols.frame(entry);
+                let stack = format!("{base};{name}");
+                profiler.frames.push(Frame { stack, ret });
+                profiler.call(name);
+            }
+            Flow::Jump(target) => {
+                if let Some(depth) = profiler.frames.iter().rposition(|f| f.ret == target) {
+                    profiler.frames.truncate(depth);
+                }
+            }
+        }
+    });
+}
+
+/// A reader of the `.pvm` blob encodings
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    /// Take bytes from the reader
+    fn take(&mut self, len: usize) -> Result<&'b [u8]> {
+        if self.0.len() < len {
+            bail!("unexpected end of pvm blob");
+        }
+
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    /// Read a variable-length integer
+    fn varint(&mut self) -> Result<u32> {
+        let head = self.take(1)?[0];
+        let len = head.leading_ones() as usize;
+        if len > 4 {
+            bail!("invalid varint in pvm blob");
+        }
+
+        let high = (head as u32 & (0xff >> len)).checked_shl(8 * len as u32);
+        let low = self
+            .take(len)?
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | *b as u32);
+        Ok(high.unwrap_or_default() | low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polkavm_common::{
+        program::{
+            Instruction, SECTION_OPT_DEBUG_LINE_PROGRAM_RANGES, SECTION_OPT_DEBUG_LINE_PROGRAMS,
+            SECTION_OPT_DEBUG_STRINGS, VERSION_DEBUG_LINE_PROGRAM_V1,
+        },
+        varint,
+        writer::ProgramBlobBuilder,
+    };
+
+    /// A `.pvm` blob of three functions, the first one exported
+    ///
+    /// The debug info names the first two functions like the linker does
+    /// with the ELF symbol table.
+    fn blob() -> Vec<u8> {
+        let mut builder = ProgramBlobBuilder::new_64bit();
+        builder.set_code(
+            &[Instruction::trap, Instruction::trap, Instruction::trap],
+            &[],
+        );
+        builder.add_export_by_basic_block(0, b"refine");
+
+        let mut strings = Vec::new();
+        let mut string = |value: &str| {
+            let offset = strings.len() as u32;
+            strings.push(value.len() as u8);
+            strings.extend(value.as_bytes());
+            offset
+        };
+        let names = [("", ""), ("stoken", "refine"), ("stoken", "transfer")];
+        let names = names.map(|(namespace, name)| (string(namespace), string(name)));
+
+        let (mut programs, mut ranges) = (vec![VERSION_DEBUG_LINE_PROGRAM_V1], Vec::new());
+        for (pc, (namespace, name)) in names.into_iter().enumerate().skip(1) {
+            let pc = pc as u32 - 1;
+            ranges.extend(pc.to_le_bytes());
+            ranges.extend((pc + 1).to_le_bytes());
+            ranges.extend((programs.len() as u32).to_le_bytes());
+
+            // set the namespace, the name and the depth, then finish one instruction
+            programs.extend([5, namespace as u8, 6, name as u8, 10, 1, 14, 0]);
+        }
+
+        builder.add_custom_section(SECTION_OPT_DEBUG_STRINGS, strings);
+        builder.add_custom_section(SECTION_OPT_DEBUG_LINE_PROGRAMS, programs);
+        builder.add_custom_section(SECTION_OPT_DEBUG_LINE_PROGRAM_RANGES, ranges);
+        builder.into_vec().unwrap()
+    }
+
+    #[test]
+    fn read_varints() {
+        for value in [
+            0,
+            1,
+            0x7f,
+            0x80,
+            0x3fff,
+            0x4000,
+            0x1f_ffff,
+            0x20_0000,
+            0x0fff_ffff,
+            0x1000_0000,
+            u32::MAX,
+        ] {
+            let mut buffer = [0; varint::MAX_VARINT_LENGTH];
+            let len = varint::write_varint(value, &mut buffer);
+            let mut reader = Reader(&buffer[..len]);
+            assert_eq!(reader.varint().unwrap(), value, "{value:#x}");
+            assert!(reader.0.is_empty());
+        }
+
+        assert!(Reader(&[0x80]).varint().is_err(), "truncated");
+        assert!(Reader(&[0xfe, 0, 0, 0, 0, 0, 0]).varint().is_err());
+        assert!(Reader(&[]).varint().is_err());
+    }
+
+    #[test]
+    fn load_symbols_from_pvm() {
+        let symbols = Symbols::from_pvm(&self::blob()).unwrap();
+        assert_eq!(symbols.get(0), Some("refine"), "exports win");
+        assert_eq!(symbols.get(1), Some("stoken::transfer"));
+        assert_eq!(symbols.frame(2), "0x2");
+
+        let mut stripped = ProgramBlobBuilder::new_64bit();
+        stripped.set_code(&[Instruction::trap], &[]);
+        stripped.add_export_by_basic_block(0, b"accumulate");
+        let symbols = Symbols::from_pvm(&stripped.into_vec().unwrap()).unwrap();
+        assert_eq!(symbols.0.len(), 1);
+        assert_eq!(symbols.get(0), Some("accumulate"));
+        assert!(Symbols::from_pvm(b"ELF\0").is_err());
+    }
+
+    #[test]
+    fn fold_call_stacks() {
+        let mut symbols = Symbols::default();
+        symbols.insert(10, "transfer");
+        self::start(symbols);
+        self::enter("accumulate");
+        self::record(1, None, Flow::Next);
+        self::record(2, None, Flow::Call { entry: 10, ret: 4 });
+        self::record(3, None, Flow::Call { entry: 20, ret: 12 });
+        self::record(4, Some(100), Flow::Next);
+        self::record(5, None, Flow::Jump(12));
+        self::record(6, None, Flow::Jump(4));
+        self::record(7, None, Flow::Jump(u32::MAX));
+        let profile = self::stop();
+
+        let stacks = [
+            ("accumulate", 1 + 2 + 7),
+            ("accumulate;transfer", 3 + 6),
+            ("accumulate;transfer;0x14", 5),
+            ("accumulate;transfer;0x14;host:log", 4),
+        ];
+        assert_eq!(
+            profile.stacks,
+            stacks.map(|(stack, gas)| (stack.to_string(), gas)).into()
+        );
+        assert_eq!(profile.total(), 28);
+        assert_eq!(profile.folded().lines().count(), 4);
+        assert!(!self::enabled());
+
+        let usage = profile.usage().into_iter().collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            usage["transfer"],
+            Usage {
+                calls: 1,
+                self_gas: 9,
+                total_gas: 18
+            }
+        );
+        assert_eq!(usage["accumulate"].total_gas, 28);
+        assert_eq!(profile.host_calls().len(), 1);
+        assert_eq!(profile.functions().len(), 3);
+
+        let mut merged = profile.clone();
+        merged.merge(profile);
+        assert_eq!(merged.total(), 56);
+        assert_eq!(merged.calls["host:log"], 2);
+    }
+}
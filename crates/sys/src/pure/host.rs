@@ -0,0 +1,816 @@
+//! Host calls of the PVM invocations
+
+use crate::{
+    constants::Constants,
+    logs::{self, Log},
+    pure::vm::{Exit, Vm},
+};
+use service::{
+    CORES_COUNT, OpaqueHash, SEGMENT_SIZE, ServiceId,
+    api::{AccumulateState, ValidatorData},
+    service::{ServiceAccount, WorkItem, WorkPackage, result::Segment},
+    vm::{AccumulateItem, DeferredTransfer},
+};
+use std::collections::BTreeMap;
+
+/// The gas charged for each host call
+const HOST_CALL_GAS: u64 = 10;
+
+/// The minimum index of the created services
+const MIN_SERVICE: u64 = 1 << 16;
+
+/// The range of the created service indexes
+const SERVICE_RANGE: u64 = (1 << 32) - MIN_SERVICE - (1 << 8);
+
+/// The prefix of the storage keys
+const STORAGE_PREFIX: [u8; 4] = [255, 255, 255, 255];
+
+/// The size of an encoded validator key
+const VALIDATOR_SIZE: usize = 336;
+
+/// The item does not exist
+const NONE: u64 = u64::MAX;
+
+/// The name is unknown
+const WHAT: u64 = u64::MAX - 1;
+
+/// The index is not known
+const WHO: u64 = u64::MAX - 3;
+
+/// The storage is full
+const FULL: u64 = u64::MAX - 4;
+
+/// The core index is not known
+const CORE: u64 = u64::MAX - 5;
+
+/// The balance is insufficient
+const CASH: u64 = u64::MAX - 6;
+
+/// The item is in an unexpected state
+const HUH: u64 = u64::MAX - 8;
+
+/// The call succeeded
+const OK: u64 = 0;
+
+/// Data available to the fetch host call
+#[derive(Debug, Default)]
+pub struct Fetch {
+    /// The work package
+    pub package: Option<WorkPackage>,
+
+    /// The output of the authorizer
+    pub auth_output: Option<Vec<u8>>,
+
+    /// The index of the refined work item
+    pub index: Option<usize>,
+
+    /// The imported segments of all work items
+    pub imports: Vec<Vec<Segment>>,
+
+    /// The extrinsics of all work items
+    pub extrinsics: Vec<Vec<Vec<u8>>>,
+
+    /// The entropy of the accumulation, the first entry of the buffer
+    pub entropy: Option<OpaqueHash>,
+
+    /// The accumulate items
+    pub items: Option<Vec<AccumulateItem>>,
+}
+
+/// Partial state of the accumulation
+#[derive(Debug, Clone)]
+pub struct Partial {
+    /// The accumulate state
+    pub state: AccumulateState,
+
+    /// The index of the next created service
+    pub next: ServiceId,
+
+    /// The deferred transfers
+    pub transfers: Vec<DeferredTransfer>,
+
+    /// The yielded accumulation output
+    pub yielded: Option<OpaqueHash>,
+
+    /// The provided preimages
+    pub provisions: Vec<(ServiceId, Vec<u8>)>,
+}
+
+impl Partial {
+    /// Create the partial state of the accumulating service
+    pub fn new(state: AccumulateState, service: ServiceId, timeslot: u32) -> Self {
+        let mut seed = service.to_le_bytes().to_vec();
+        seed.extend_from_slice(&state.entropy[0]);
+        seed.extend_from_slice(&timeslot.to_le_bytes());
+        let hash = service::blake2b(&seed);
+        let index = u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) as u64;
+        let next = self::check(&state.accounts, index % SERVICE_RANGE + MIN_SERVICE);
+        Self {
+            state,
+            next,
+            transfers: Vec::new(),
+            yielded: None,
+            provisions: Vec::new(),
+        }
+    }
+
+    /// Get the account of the given service
+    fn account(&mut self, service: ServiceId) -> Result<&mut ServiceAccount, Exit> {
+        self.state.accounts.get_mut(&service).ok_or(Exit::Panic)
+    }
+}
+
+/// The host environment of an invocation
+#[derive(Debug, Default)]
+pub struct Host {
+    /// The protocol constants
+    pub constants: Constants,
+
+    /// The invoked service
+    pub service: ServiceId,
+
+    /// The current timeslot
+    pub timeslot: u32,
+
+    /// The service accounts of the authorize and refine invocations
+    pub accounts: BTreeMap<ServiceId, ServiceAccount>,
+
+    /// The data available to the fetch host call
+    pub fetch: Fetch,
+
+    /// The index of the first export of the refined work item
+    pub export_offset: u64,
+
+    /// The exported segments of the refine invocation
+    pub exports: Option<Vec<Segment>>,
+
+    /// The regular partial state of the accumulate invocation
+    pub regular: Option<Partial>,
+
+    /// The exceptional partial state of the accumulate invocation
+    pub exceptional: Option<Partial>,
+}
+
+impl Host {
+    /// Dispatch a host call
+    pub fn call(&mut self, id: u32, vm: &mut Vm) -> Result<(), Exit> {
+        if !vm.charge(HOST_CALL_GAS) {
+            return Err(Exit::OutOfGas);
+        }
+
+        let refine = self.exports.is_some();
+        let accumulate = self.regular.is_some();
+        let result = match id {
+            0 => vm.gas.max(0) as u64,
+            1 => self.fetch(vm)?,
+            2 => self.lookup(vm)?,
+            3 => self.read(vm)?,
+            4 if accumulate => self.write(vm)?,
+            5 => self.info(vm)?,
+            6 if refine => self.historical_lookup(vm)?,
+            7 if refine => self.export(vm)?,
+            14 if accumulate => self.bless(vm)?,
+            15 if accumulate => self.assign(vm)?,
+            16 if accumulate => self.designate(vm)?,
+            17 if accumulate => self.checkpoint(vm),
+            18 if accumulate => self.new_service(vm)?,
+            19 if accumulate => self.upgrade(vm)?,
+            20 if accumulate => self.transfer(vm)?,
+            21 if accumulate => self.eject(vm)?,
+            22 if accumulate => self.query(vm)?,
+            23 if accumulate => self.solicit(vm)?,
+            24 if accumulate => self.forget(vm)?,
+            25 if accumulate => self.yield_output(vm)?,
+            26 if accumulate => self.provide(vm)?,
+            100 => self.log(vm),
+            _ => WHAT,
+        };
+
+        vm.regs[7] = result;
+        Ok(())
+    }
+
+    /// Get the service accounts visible to the invocation
+    fn accounts(&self) -> &BTreeMap<ServiceId, ServiceAccount> {
+        match &self.regular {
+            Some(partial) => &partial.state.accounts,
+            None => &self.accounts,
+        }
+    }
+
+    /// Get the regular partial state
+    fn partial(&mut self) -> &mut Partial {
+        self.regular.as_mut().expect("checked in the dispatcher")
+    }
+
+    /// Resolve the service index of a host call argument
+    fn target(&self, service: u64) -> u64 {
+        if service == u64::MAX {
+            self.service as u64
+        } else {
+            service
+        }
+    }
+
+    /// Get the account of a host call argument
+    fn account(&self, service: u64) -> Option<&ServiceAccount> {
+        let service = ServiceId::try_from(self.target(service)).ok()?;
+        self.accounts().get(&service)
+    }
+
+    /// Fetch the data of the invocation
+    fn fetch(&self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o, f, l, kind, a, b] = self::regs(vm);
+        let data = self.fetch_data(kind, a as usize, b as usize);
+        self::respond(vm, data.as_deref(), o, f, l)
+    }
+
+    /// Get the data of the given fetch kind
+    fn fetch_data(&self, kind: u64, a: usize, b: usize) -> Option<Vec<u8>> {
+        let fetch = &self.fetch;
+        let package = fetch.package.as_ref();
+        match kind {
+            0 => Some(self.constants.encode()),
+            1 => fetch.entropy.map(|entropy| entropy.to_vec()),
+            2 => fetch.auth_output.clone(),
+            3 => fetch.extrinsics.get(a)?.get(b).cloned(),
+            4 => fetch.extrinsics.get(fetch.index?)?.get(a).cloned(),
+            5 => fetch
+                .imports
+                .get(a)?
+                .get(b)
+                .map(|segment| segment.0.to_vec()),
+            6 => fetch
+                .imports
+                .get(fetch.index?)?
+                .get(a)
+                .map(|segment| segment.0.to_vec()),
+            7 => codec::encode(package?).ok(),
+            8 => Some(package?.config.clone()),
+            9 => Some(package?.authorization.clone()),
+            10 => codec::encode(&package?.context).ok(),
+            11 => {
+                let items = &package?.items;
+                let mut out = self::natural(items.len() as u64);
+                items
+                    .iter()
+                    .for_each(|item| out.extend(self::summary(item)));
+                Some(out)
+            }
+            12 => package?.items.get(a).map(self::summary),
+            13 => package?.items.get(a).map(|item| item.payload.clone()),
+            14 => codec::encode(fetch.items.as_ref()?).ok(),
+            15 => codec::encode(fetch.items.as_ref()?.get(a)?).ok(),
+            _ => None,
+        }
+    }
+
+    /// Lookup a preimage of a service
+    fn lookup(&self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [s, h, o, f, l] = self::regs(vm);
+        let hash = self::hash(vm, h)?;
+        let data = self
+            .account(s)
+            .and_then(|account| account.preimage.get(&hash));
+        self::respond(vm, data.map(Vec::as_slice), o, f, l)
+    }
+
+    /// Read the storage of a service
+    ///
+    /// With a null output pointer and a non-zero length, the requested range
+    /// is written to the heap and its address is returned, this is how
+    /// `jade::storage::read` reads the values.
+    fn read(&self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [s, ko, kz, o, f, l] = self::regs(vm);
+        let key = vm.memory.read_vec(ko, kz)?;
+        let service = self.target(s);
+        let data = self.account(s).and_then(|account| {
+            let key = self::storage_key(service as ServiceId, &key);
+            account.storage.get(key.as_ref())
+        });
+
+        match data {
+            Some(data) if o == 0 && l > 0 => {
+                let start = f.min(data.len() as u64) as usize;
+                let len = l.min((data.len() - start) as u64) as usize;
+                let address = vm.memory.sbrk(len as u64).ok_or(Exit::Panic)? as u64;
+                vm.memory.write(address, &data[start..start + len])?;
+                Ok(address)
+            }
+            data => self::respond(vm, data.map(Vec::as_slice), o, f, l),
+        }
+    }
+
+    /// Write the storage of the accumulating service
+    ///
+    /// NOTE: the storage deposit is not checked
+    fn write(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [ko, kz, vo, vz] = self::regs(vm);
+        let key = vm.memory.read_vec(ko, kz)?;
+        let value = vm.memory.read_vec(vo, vz)?;
+        let service = self.service;
+        let account = self.partial().account(service)?;
+        let key = self::storage_key(service, &key);
+        let previous = if value.is_empty() {
+            account.storage.remove(key.as_ref())
+        } else {
+            account.storage.insert(key.into(), value)
+        };
+
+        Ok(previous.map(|data| data.len() as u64).unwrap_or(NONE))
+    }
+
+    /// Get the info of a service
+    fn info(&self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [s, o, f, l] = self::regs(vm);
+        let data = self
+            .account(s)
+            .and_then(|account| codec::encode(&account.info).ok());
+        self::respond(vm, data.as_deref(), o, f, l)
+    }
+
+    /// Lookup a preimage available at the current timeslot
+    fn historical_lookup(&self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [s, h, o, f, l] = self::regs(vm);
+        let hash = self::hash(vm, h)?;
+        let data = self.account(s).and_then(|account| {
+            let data = account.preimage.get(&hash)?;
+            let history = account.lookup.get(&(hash, data.len() as u32))?;
+            self::available(history, self.timeslot).then_some(data)
+        });
+        self::respond(vm, data.map(Vec::as_slice), o, f, l)
+    }
+
+    /// Export a segment
+    fn export(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [p, z] = self::regs(vm);
+        let mut segment = [0; SEGMENT_SIZE];
+        let len = z.min(SEGMENT_SIZE as u64) as usize;
+        vm.memory.read(p, &mut segment[..len])?;
+
+        let max = self.constants.max_exports as u64;
+        let exports = self.exports.as_mut().expect("checked in the dispatcher");
+        let index = self.export_offset + exports.len() as u64;
+        if index >= max {
+            return Ok(FULL);
+        }
+
+        exports.push(Segment(segment));
+        Ok(index)
+    }
+
+    /// Set the privileged services
+    fn bless(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [m, a, v, r, o, n] = self::regs(vm);
+        let mut assign = [0; CORES_COUNT];
+        for (core, id) in vm
+            .memory
+            .read_vec(a, 4 * CORES_COUNT as u64)?
+            .chunks(4)
+            .enumerate()
+        {
+            assign[core] = u32::from_le_bytes(id.try_into().expect("chunked"));
+        }
+
+        let always = vm
+            .memory
+            .read_vec(o, 12 * n)?
+            .chunks(12)
+            .map(|pair| {
+                let service = u32::from_le_bytes(pair[..4].try_into().expect("chunked"));
+                let gas = u64::from_le_bytes(pair[4..].try_into().expect("chunked"));
+                (service, gas)
+            })
+            .collect();
+
+        let (Ok(bless), Ok(designate), Ok(register)) = (
+            ServiceId::try_from(m),
+            ServiceId::try_from(v),
+            ServiceId::try_from(r),
+        ) else {
+            return Ok(WHO);
+        };
+
+        let service = self.service;
+        let privileges = &mut self.partial().state.privileges;
+        if privileges.bless != service {
+            return Ok(HUH);
+        }
+
+        privileges.bless = bless;
+        privileges.assign = assign;
+        privileges.designate = designate;
+        privileges.register = register;
+        privileges.always_acc = always;
+        Ok(OK)
+    }
+
+    /// Assign the authorizer queue of a core
+    fn assign(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [c, o, a] = self::regs(vm);
+        let size = self.constants.auth_queue as u64;
+        let queue = vm
+            .memory
+            .read_vec(o, 32 * size)?
+            .chunks(32)
+            .map(|hash| OpaqueHash::try_from(hash).expect("chunked"))
+            .collect::<Vec<_>>();
+
+        let service = self.service;
+        let state = &mut self.partial().state;
+        let core = c as usize;
+        let Some(assigner) = state.privileges.assign.get(core).copied() else {
+            return Ok(CORE);
+        };
+
+        if assigner != service {
+            return Ok(HUH);
+        }
+
+        let Ok(next) = ServiceId::try_from(a) else {
+            return Ok(WHO);
+        };
+
+        let Some(slot) = state.authorization.get_mut(core) else {
+            return Ok(CORE);
+        };
+
+        *slot = queue;
+        state.privileges.assign[core] = next;
+        Ok(OK)
+    }
+
+    /// Designate the next validator keys
+    fn designate(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o] = self::regs(vm);
+        let count = self.partial().state.validators.len();
+        let keys = vm
+            .memory
+            .read_vec(o, (count * VALIDATOR_SIZE) as u64)?
+            .chunks(VALIDATOR_SIZE)
+            .map(|key| ValidatorData {
+                bandersnatch: key[..32].try_into().expect("chunked"),
+                ed25519: key[32..64].try_into().expect("chunked"),
+                bls: key[64..208].try_into().expect("chunked"),
+                metadata: key[208..].try_into().expect("chunked"),
+            })
+            .collect::<Vec<_>>();
+
+        let service = self.service;
+        let state = &mut self.partial().state;
+        if state.privileges.designate != service {
+            return Ok(HUH);
+        }
+
+        state.validators = keys.try_into().map_err(|_| Exit::Panic)?;
+        Ok(OK)
+    }
+
+    /// Checkpoint the regular partial state
+    fn checkpoint(&mut self, vm: &mut Vm) -> u64 {
+        self.exceptional = self.regular.clone();
+        vm.gas.max(0) as u64
+    }
+
+    /// Create a new service
+    ///
+    /// NOTE: the endowment of the new service is not checked
+    fn new_service(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o, l] = self::regs(vm);
+        let code = self::hash(vm, o)?;
+        let Ok(len) = u32::try_from(l) else {
+            return Ok(HUH);
+        };
+
+        let timeslot = self.timeslot;
+        let partial = self.partial();
+        let index = partial.next;
+        let mut account = ServiceAccount {
+            index,
+            ..Default::default()
+        };
+        account.info.code = code;
+        account.info.creation = timeslot as _;
+        account.lookup.insert((code, len), Vec::new());
+        partial.state.accounts.insert(index, account);
+
+        let next = (index as u64 - MIN_SERVICE + 42) % SERVICE_RANGE + MIN_SERVICE;
+        partial.next = self::check(&partial.state.accounts, next);
+        Ok(index as u64)
+    }
+
+    /// Upgrade the code of the accumulating service
+    fn upgrade(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o] = self::regs(vm);
+        let code = self::hash(vm, o)?;
+        let service = self.service;
+        self.partial().account(service)?.info.code = code;
+        Ok(OK)
+    }
+
+    /// Transfer balance to another service
+    fn transfer(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [d, a, l, o] = self::regs(vm);
+        let memo = vm.memory.read_vec(o, self.constants.memo_size as u64)?;
+        let service = self.service;
+        let partial = self.partial();
+        let Some(to) = ServiceId::try_from(d)
+            .ok()
+            .filter(|to| partial.state.accounts.contains_key(to))
+        else {
+            return Ok(WHO);
+        };
+
+        let account = partial.account(service)?;
+        if account.info.balance < a {
+            return Ok(CASH);
+        }
+
+        if !vm.charge(l) {
+            return Err(Exit::OutOfGas);
+        }
+
+        account.info.balance -= a;
+        partial.transfers.push(DeferredTransfer {
+            sender: service,
+            recipient: to,
+            amount: a,
+            memo,
+            gas_limit: l,
+        });
+        Ok(OK)
+    }
+
+    /// Eject a service which has been marked for removal
+    fn eject(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [d, o] = self::regs(vm);
+        let hash = self::hash(vm, o)?;
+        let service = self.service;
+        let expunge = self.constants.expunge_period;
+        let timeslot = self.timeslot;
+        let partial = self.partial();
+
+        let mut marker = OpaqueHash::default();
+        marker[..4].copy_from_slice(&service.to_le_bytes());
+        let Some(target) = ServiceId::try_from(d)
+            .ok()
+            .filter(|target| *target != service)
+            .and_then(|target| partial.state.accounts.get(&target))
+            .filter(|target| target.info.code == marker)
+        else {
+            return Ok(WHO);
+        };
+
+        let expired = target
+            .lookup
+            .iter()
+            .find(|((h, _), _)| *h == hash)
+            .is_some_and(|(_, history)| {
+                matches!(history.as_slice(), [_, y] if (*y as u64) + (expunge as u64) < timeslot as u64)
+            });
+        if !expired {
+            return Ok(HUH);
+        }
+
+        let target = partial
+            .state
+            .accounts
+            .remove(&(d as ServiceId))
+            .expect("checked");
+        partial.account(service)?.info.balance += target.info.balance;
+        Ok(OK)
+    }
+
+    /// Query the lookup history of a preimage
+    fn query(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o, z] = self::regs(vm);
+        let hash = self::hash(vm, o)?;
+        let service = self.service;
+        let history = self
+            .partial()
+            .account(service)?
+            .lookup
+            .get(&(hash, z as u32))
+            .cloned();
+
+        let (a, b) = match history.as_deref() {
+            None => (NONE, 0),
+            Some([]) => (0, 0),
+            Some([x]) => (1 + ((*x as u64) << 32), 0),
+            Some([x, y]) => (2 + ((*x as u64) << 32), *y as u64),
+            Some([x, y, z, ..]) => (3 + ((*x as u64) << 32), *y as u64 + ((*z as u64) << 32)),
+        };
+
+        vm.regs[8] = b;
+        Ok(a)
+    }
+
+    /// Solicit a preimage
+    ///
+    /// NOTE: the storage deposit is not checked
+    fn solicit(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o, z] = self::regs(vm);
+        let hash = self::hash(vm, o)?;
+        let (service, timeslot) = (self.service, self.timeslot);
+        let lookup = &mut self.partial().account(service)?.lookup;
+        match lookup.get_mut(&(hash, z as u32)) {
+            None => {
+                lookup.insert((hash, z as u32), Vec::new());
+            }
+            Some(history) if history.len() == 2 => history.push(timeslot),
+            Some(_) => return Ok(HUH),
+        }
+
+        Ok(OK)
+    }
+
+    /// Forget a preimage
+    fn forget(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o, z] = self::regs(vm);
+        let hash = self::hash(vm, o)?;
+        let (service, timeslot) = (self.service, self.timeslot);
+        let expunge = self.constants.expunge_period;
+        let expired = |slot: u32| slot as u64 + (expunge as u64) < timeslot as u64;
+        let account = self.partial().account(service)?;
+        let key = (hash, z as u32);
+        let Some(history) = account.lookup.get(&key).cloned() else {
+            return Ok(HUH);
+        };
+
+        match history.as_slice() {
+            [] => {
+                account.lookup.remove(&key);
+                account.preimage.remove(&hash);
+            }
+            [_, y] if expired(*y) => {
+                account.lookup.remove(&key);
+                account.preimage.remove(&hash);
+            }
+            [x] => {
+                account.lookup.insert(key, vec![*x, timeslot]);
+            }
+            [_, y, w] if expired(*y) => {
+                account.lookup.insert(key, vec![*w, timeslot]);
+            }
+            _ => return Ok(HUH),
+        }
+
+        Ok(OK)
+    }
+
+    /// Yield the accumulation output
+    fn yield_output(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [o] = self::regs(vm);
+        let hash = self::hash(vm, o)?;
+        self.partial().yielded = Some(hash);
+        Ok(OK)
+    }
+
+    /// Provide a preimage solicited by a service
+    fn provide(&mut self, vm: &mut Vm) -> Result<u64, Exit> {
+        let [s, o, z] = self::regs(vm);
+        let data = vm.memory.read_vec(o, z)?;
+        let target = self.target(s);
+        let partial = self.partial();
+        let Some(account) = ServiceId::try_from(target)
+            .ok()
+            .and_then(|target| partial.state.accounts.get(&target))
+        else {
+            return Ok(WHO);
+        };
+
+        let hash = service::blake2b(&data);
+        let solicited = account
+            .lookup
+            .get(&(hash, data.len() as u32))
+            .is_some_and(|history| history.is_empty());
+        let provision = (account.index, data);
+        if !solicited || partial.provisions.contains(&provision) {
+            return Ok(HUH);
+        }
+
+        partial.provisions.push(provision);
+        Ok(OK)
+    }
+
+    /// Log a message of the service
+    fn log(&self, vm: &mut Vm) -> u64 {
+        let [level, tp, tl, mp, ml] = self::regs(vm);
+        let target = if tp == 0 {
+            Vec::new()
+        } else {
+            vm.memory.read_vec(tp, tl).unwrap_or_default()
+        };
+        let message = vm.memory.read_vec(mp, ml).unwrap_or_default();
+        let target = String::from_utf8_lossy(&target);
+        let message = String::from_utf8_lossy(&message);
+        let service = self.service;
+        match level {
+            0 => tracing::error!(service, %target, "{message}"),
+            1 => tracing::warn!(service, %target, "{message}"),
+            2 => tracing::info!(service, %target, "{message}"),
+            3 => tracing::debug!(service, %target, "{message}"),
+            _ => tracing::trace!(service, %target, "{message}"),
+        }
+
+        if logs::enabled() {
+            logs::record(Log {
+                service,
+                level,
+                target: target.into_owned(),
+                message: message.into_owned(),
+            });
+        }
+
+        vm.regs[7]
+    }
+}
+
+/// Get the host call arguments starting from the 7th register
+fn regs<const N: usize>(vm: &Vm) -> [u64; N] {
+    vm.regs[7..7 + N].try_into().expect("enough registers")
+}
+
+/// Read a hash from the memory
+fn hash(vm: &Vm, address: u64) -> Result<OpaqueHash, Exit> {
+    let mut hash = OpaqueHash::default();
+    vm.memory.read(address, &mut hash)?;
+    Ok(hash)
+}
+
+/// Write the requested range of the data to the memory
+fn respond(vm: &mut Vm, data: Option<&[u8]>, o: u64, f: u64, l: u64) -> Result<u64, Exit> {
+    let Some(data) = data else {
+        return Ok(NONE);
+    };
+
+    let start = f.min(data.len() as u64) as usize;
+    let len = l.min((data.len() - start) as u64) as usize;
+    vm.memory.write(o, &data[start..start + len])?;
+    Ok(data.len() as u64)
+}
+
+/// Find the first free service index from the given one
+fn check(accounts: &BTreeMap<ServiceId, ServiceAccount>, index: u64) -> ServiceId {
+    let mut index = index;
+    while accounts.contains_key(&(index as ServiceId)) {
+        index = (index - MIN_SERVICE + 1) % SERVICE_RANGE + MIN_SERVICE;
+    }
+
+    index as ServiceId
+}
+
+/// If a preimage is available at the given timeslot
+fn available(history: &[u32], slot: u32) -> bool {
+    match history {
+        [x] => *x <= slot,
+        [x, y] => *x <= slot && slot < *y,
+        [x, y, z] => (*x <= slot && slot < *y) || *z <= slot,
+        _ => false,
+    }
+}
+
+/// Compute the state key of a storage entry
+pub fn storage_key(service: ServiceId, key: &[u8]) -> [u8; 31] {
+    let mut hashed = STORAGE_PREFIX.to_vec();
+    hashed.extend_from_slice(key);
+    let hash = service::blake2b(&hashed);
+
+    let mut state = [0; 31];
+    for (i, (a, b)) in service.to_le_bytes().iter().zip(&hash[..4]).enumerate() {
+        state[i * 2] = *a;
+        state[i * 2 + 1] = *b;
+    }
+    state[8..].copy_from_slice(&hash[4..27]);
+    state
+}
+
+/// Encode the summary of a work item
+fn summary(item: &WorkItem) -> Vec<u8> {
+    let mut out = Vec::with_capacity(62);
+    out.extend_from_slice(&item.service.to_le_bytes());
+    out.extend_from_slice(&item.code_hash);
+    out.extend_from_slice(&item.refine_gas_limit.to_le_bytes());
+    out.extend_from_slice(&item.accumulate_gas_limit.to_le_bytes());
+    out.extend_from_slice(&item.export_count.to_le_bytes());
+    out.extend_from_slice(&(item.import_segments.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(item.extrinsic.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(item.payload.len() as u32).to_le_bytes());
+    out
+}
+
+/// Encode a general natural number
+fn natural(value: u64) -> Vec<u8> {
+    for len in 0..8 {
+        if value < 1 << (7 * (len + 1)) {
+            let head = (0xffu16 << (8 - len)) as u8 | (value >> (8 * len)) as u8;
+            let mut out = vec![head];
+            out.extend_from_slice(&value.to_le_bytes()[..len]);
+            return out;
+        }
+    }
+
+    let mut out = vec![0xff];
+    out.extend_from_slice(&value.to_le_bytes());
+    out
+}
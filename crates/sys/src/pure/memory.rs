@@ -0,0 +1,232 @@
+//! Paged memory of the PVM
+
+use crate::pure::program::Program;
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+
+/// The page size
+pub const PAGE_SIZE: u32 = 1 << 12;
+
+/// The zone size
+pub const ZONE_SIZE: u32 = 1 << 16;
+
+/// The maximum size of the invocation arguments
+pub const INPUT_SIZE: u32 = 1 << 24;
+
+/// The address of the invocation arguments
+pub const ARGS_ADDRESS: u32 = (u32::MAX - ZONE_SIZE - INPUT_SIZE) + 1;
+
+/// The top of the stack
+pub const STACK_ADDRESS: u32 = (u32::MAX - 2 * ZONE_SIZE - INPUT_SIZE) + 1;
+
+/// Access of a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// The page is readable
+    ReadOnly,
+
+    /// The page is readable and writable
+    ReadWrite,
+}
+
+/// A memory fault at the given address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault(pub u64);
+
+/// A page of the memory
+#[derive(Debug, Clone)]
+struct Page {
+    data: Box<[u8]>,
+    access: Access,
+}
+
+/// Paged memory of the PVM
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    /// The mapped pages
+    pages: BTreeMap<u32, Page>,
+
+    /// The current top of the heap
+    heap: u32,
+}
+
+impl Memory {
+    /// Initialize the memory of the standard program
+    pub fn standard(program: &Program, args: &[u8]) -> Result<Self> {
+        let ro_len = program.ro_data.len() as u64;
+        let rw_len = program.rw_data.len() as u64 + program.rw_pages as u64 * PAGE_SIZE as u64;
+        let stack = program.stack_size as u64;
+        let total =
+            5 * ZONE_SIZE as u64 + zone(ro_len) + zone(rw_len) + zone(stack) + INPUT_SIZE as u64;
+        if total > 1 << 32 || args.len() as u64 > INPUT_SIZE as u64 {
+            bail!("program does not fit into the memory");
+        }
+
+        let mut memory = Self::default();
+        let ro = ZONE_SIZE;
+        memory.map(ro, page(ro_len), Access::ReadOnly);
+        memory.init(ro, &program.ro_data);
+
+        let rw = 2 * ZONE_SIZE + zone(ro_len) as u32;
+        memory.map(rw, page(rw_len), Access::ReadWrite);
+        memory.init(rw, &program.rw_data);
+        memory.heap = rw + page(rw_len);
+
+        memory.map(STACK_ADDRESS - page(stack), page(stack), Access::ReadWrite);
+        memory.map(ARGS_ADDRESS, page(args.len() as u64), Access::ReadOnly);
+        memory.init(ARGS_ADDRESS, args);
+        Ok(memory)
+    }
+
+    /// Read bytes from the memory
+    pub fn read(&self, address: u64, buf: &mut [u8]) -> Result<(), Fault> {
+        let mut offset = 0;
+        while offset < buf.len() {
+            let current = address + offset as u64;
+            let page = self.page(current)?;
+            let start = (current % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE as usize - start).min(buf.len() - offset);
+            buf[offset..offset + len].copy_from_slice(&page.data[start..start + len]);
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    /// Read a vector from the memory
+    pub fn read_vec(&self, address: u64, len: u64) -> Result<Vec<u8>, Fault> {
+        if len > INPUT_SIZE as u64 * 4 {
+            return Err(Fault(address));
+        }
+
+        let mut buf = vec![0; len as usize];
+        self.read(address, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read a fixed-length little-endian integer from the memory
+    pub fn read_int(&self, address: u64, len: usize) -> Result<u64, Fault> {
+        let mut buf = [0; 8];
+        self.read(address, &mut buf[..len])?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Write bytes to the memory
+    pub fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Fault> {
+        // check the access before writing anything
+        let mut offset = 0;
+        while offset < data.len() {
+            let current = address + offset as u64;
+            if self.page(current)?.access != Access::ReadWrite {
+                return Err(Fault(current));
+            }
+            offset += PAGE_SIZE as usize - (current % PAGE_SIZE as u64) as usize;
+        }
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let current = address + offset as u64;
+            let start = (current % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE as usize - start).min(data.len() - offset);
+            let page = self
+                .pages
+                .get_mut(&((current / PAGE_SIZE as u64) as u32))
+                .expect("checked");
+            page.data[start..start + len].copy_from_slice(&data[offset..offset + len]);
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    /// Write a fixed-length little-endian integer to the memory
+    pub fn write_int(&mut self, address: u64, value: u64, len: usize) -> Result<(), Fault> {
+        self.write(address, &value.to_le_bytes()[..len])
+    }
+
+    /// Check if the given range is writable
+    pub fn is_writable(&self, address: u64, len: u64) -> bool {
+        let mut offset = 0;
+        while offset < len {
+            let current = address + offset;
+            match self.page(current) {
+                Ok(page) if page.access == Access::ReadWrite => {}
+                _ => return false,
+            }
+            offset += PAGE_SIZE as u64 - current % PAGE_SIZE as u64;
+        }
+
+        true
+    }
+
+    /// Grow the heap, returns the previous top of the heap
+    pub fn sbrk(&mut self, size: u64) -> Option<u32> {
+        let top = self.heap;
+        if size == 0 {
+            return Some(top);
+        }
+
+        let next = top as u64 + size;
+        let limit = self
+            .pages
+            .range(page(top as u64) / PAGE_SIZE..)
+            .next()
+            .map(|(index, _)| *index as u64 * PAGE_SIZE as u64)
+            .unwrap_or(1 << 32);
+        if next > limit {
+            return None;
+        }
+
+        let mapped = page(top as u64);
+        if next > mapped as u64 {
+            self.map(mapped, page(next - mapped as u64), Access::ReadWrite);
+        }
+
+        self.heap = next as u32;
+        Some(top)
+    }
+
+    /// Map zeroed pages at the given address
+    fn map(&mut self, address: u32, len: u32, access: Access) {
+        let first = address / PAGE_SIZE;
+        for index in first..first + len.div_ceil(PAGE_SIZE) {
+            self.pages.insert(
+                index,
+                Page {
+                    data: vec![0; PAGE_SIZE as usize].into_boxed_slice(),
+                    access,
+                },
+            );
+        }
+    }
+
+    /// Initialize data in the mapped pages ignoring the access
+    fn init(&mut self, address: u32, data: &[u8]) {
+        for (offset, chunk) in data.chunks(PAGE_SIZE as usize).enumerate() {
+            let index = address / PAGE_SIZE + offset as u32;
+            let page = self.pages.get_mut(&index).expect("mapped before init");
+            page.data[..chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    /// Get the page of the given address
+    fn page(&self, address: u64) -> Result<&Page, Fault> {
+        if address < ZONE_SIZE as u64 || address >= 1 << 32 {
+            return Err(Fault(address));
+        }
+
+        self.pages
+            .get(&((address / PAGE_SIZE as u64) as u32))
+            .ok_or(Fault(address))
+    }
+}
+
+/// Round up to the page size
+fn page(len: u64) -> u32 {
+    len.next_multiple_of(PAGE_SIZE as u64) as u32
+}
+
+/// Round up to the zone size
+fn zone(len: u64) -> u64 {
+    len.next_multiple_of(ZONE_SIZE as u64)
+}
@@ -0,0 +1,398 @@
+//! Pure-Rust backend of the PVM invocations
+//!
+//! Interprets the service code without the native spacevm library, the
+//! host calls are implemented in [`host`].
+
+use crate::{
+    Backend, Error,
+    profile::{self, Flow},
+    pure::{
+        host::{Fetch, Host, Partial},
+        program::Program,
+        vm::{Exit, Vm},
+    },
+    trace::{self, HostCall, Step},
+};
+use anyhow::{Result, anyhow};
+use service::{
+    OpaqueHash,
+    api::{AccumulateArgs, Accumulated, AuthorizeArgs, Reason, RefineArgs},
+    service::{
+        WorkExecResult,
+        result::{Executed, Refined, Segment},
+    },
+    vm::{AccumulateItem, AccumulateParams, DeferredTransfer, RefineParams},
+};
+
+pub use crate::constants::{self, Constants};
+
+pub mod host;
+pub mod memory;
+pub mod program;
+pub mod vm;
+
+#[cfg(test)]
+mod tests;
+
+/// The entry point of is_authorized
+const AUTHORIZE_ENTRY: u32 = 0;
+
+/// The entry point of refine
+const REFINE_ENTRY: u32 = 0;
+
+/// The entry point of accumulate
+const ACCUMULATE_ENTRY: u32 = 5;
+
+/// Initialize the logger
+///
+/// The pure backend logs with `tracing`, the subscriber is installed by
+/// the caller.
+///
+/// # Safety
+///
+/// This function is safe, it keeps the signature of the native backend.
+pub unsafe fn init_logger(_ansi: bool, _timer: bool) {}
+
+/// Run the authorize invocation, the pure backend only interprets
+pub fn authorize_with(backend: Backend, args: AuthorizeArgs) -> Result<Executed> {
+    self::interpret(backend, "authorize")?;
+    self::authorize(args)
+}
+
+/// Run the refine invocation, the pure backend only interprets
+pub fn refine_with(backend: Backend, args: RefineArgs) -> Result<Refined> {
+    self::interpret(backend, "refine")?;
+    self::refine(args)
+}
+
+/// Run the refine invocation with the extrinsics of the work items, the
+/// pure backend only interprets
+pub fn refine_with_extrinsics(
+    backend: Backend,
+    args: RefineArgs,
+    extrinsics: Vec<Vec<Vec<u8>>>,
+) -> Result<Refined> {
+    self::interpret(backend, "refine")?;
+    self::refine_items(args, extrinsics)
+}
+
+/// Run the accumulate invocation, the pure backend only interprets
+pub fn accumulate_with(backend: Backend, args: AccumulateArgs) -> Result<Accumulated> {
+    self::interpret(backend, "accumulate")?;
+    self::accumulate(args)
+}
+
+/// Run the accumulate invocation with the deferred transfers to the service,
+/// the pure backend only interprets
+pub fn accumulate_with_transfers(
+    backend: Backend,
+    args: AccumulateArgs,
+    transfers: Vec<DeferredTransfer>,
+) -> Result<Accumulated> {
+    self::interpret(backend, "accumulate")?;
+    self::accumulate_items(args, transfers)
+}
+
+/// Reject the recompiler, which the pure backend does not provide
+fn interpret(backend: Backend, call: &'static str) -> Result<()> {
+    match backend {
+        Backend::Interpreter => Ok(()),
+        Backend::Compiler => Err(Error::Unsupported {
+            call,
+            feature: "the compiler",
+        }
+        .into()),
+    }
+}
+
+/// Run the authorize invocation
+pub fn authorize(args: AuthorizeArgs) -> Result<Executed> {
+    let package = &args.package;
+    let code = args
+        .accounts
+        .get(&package.auth_code_host)
+        .and_then(|account| account.preimage.get(&package.auth_code_hash))
+        .ok_or_else(|| anyhow!("authorizer code not found"))?;
+
+    let constants = Constants::default();
+    let mut host = Host {
+        constants,
+        service: package.auth_code_host,
+        timeslot: args.timeslot,
+        fetch: Fetch {
+            package: Some(args.package.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let input = codec::encode(&args.core_idx)?;
+    let (exec, gas) = self::invoke(
+        code,
+        (AUTHORIZE_ENTRY, "is_authorized"),
+        constants.authorize_gas,
+        &input,
+        &mut host,
+    )?;
+    Ok(self::executed(exec, gas))
+}
+
+/// Run the refine invocation
+pub fn refine(args: RefineArgs) -> Result<Refined> {
+    self::refine_items(args, Vec::new())
+}
+
+/// Run the refine invocation with the extrinsics of all work items
+fn refine_items(args: RefineArgs, extrinsics: Vec<Vec<Vec<u8>>>) -> Result<Refined> {
+    let index = args.index;
+    let item = args
+        .package
+        .items
+        .get(index)
+        .ok_or_else(|| anyhow!("work item {index} not found"))?;
+    let code = args
+        .accounts
+        .get(&item.service)
+        .and_then(|account| account.preimage.get(&item.code_hash))
+        .ok_or_else(|| anyhow!("service code of work item {index} not found"))?
+        .clone();
+
+    let input = codec::encode(&RefineParams {
+        core: args.core as _,
+        index: args.index as _,
+        id: item.service,
+        payload: item.payload.clone(),
+        package: service::blake2b(&codec::encode(&args.package)?),
+    })?;
+
+    let gas = item.refine_gas_limit;
+    let export_count = item.export_count as usize;
+    let mut host = Host {
+        service: item.service,
+        timeslot: args.timeslot,
+        accounts: args.accounts,
+        fetch: Fetch {
+            package: Some(args.package),
+            auth_output: Some(args.auth_output),
+            index: Some(index),
+            imports: args
+                .all_imports
+                .into_iter()
+                .map(|imports| {
+                    imports
+                        .into_iter()
+                        .map(|segment| Segment(segment.0))
+                        .collect()
+                })
+                .collect(),
+            extrinsics,
+            ..Default::default()
+        },
+        export_offset: args.export_offset as u64,
+        exports: Some(Vec::new()),
+        ..Default::default()
+    };
+
+    let (mut exec, gas) = self::invoke(&code, (REFINE_ENTRY, "refine"), gas, &input, &mut host)?;
+    let mut exports = host.exports.unwrap_or_default();
+    if matches!(exec, WorkExecResult::Ok(_)) && exports.len() != export_count {
+        exec = WorkExecResult::InvalidExports;
+    }
+
+    if !matches!(exec, WorkExecResult::Ok(_)) {
+        exports = Vec::new();
+    }
+
+    Ok(Refined {
+        executed: self::executed(exec, gas),
+        segments: exports,
+    })
+}
+
+/// Run the accumulate invocation
+pub fn accumulate(args: AccumulateArgs) -> Result<Accumulated> {
+    self::accumulate_items(args, Vec::new())
+}
+
+/// Run the accumulate invocation with the deferred transfers, which precede
+/// the operands in the accumulate items
+fn accumulate_items(args: AccumulateArgs, transfers: Vec<DeferredTransfer>) -> Result<Accumulated> {
+    let service = args.service;
+    let code = args
+        .context
+        .accounts
+        .get(&service)
+        .and_then(|account| account.preimage.get(&account.info.code))
+        .ok_or_else(|| anyhow!("service code of {service} not found"))?
+        .clone();
+
+    let items = transfers
+        .into_iter()
+        .map(AccumulateItem::Transfer)
+        .chain(args.operands.into_iter().map(AccumulateItem::Operand))
+        .collect::<Vec<_>>();
+    let input = codec::encode(&AccumulateParams {
+        slot: args.timeslot as _,
+        id: service,
+        results: items.len() as _,
+    })?;
+
+    let entropy = args.context.entropy[0];
+    let partial = Partial::new(args.context, service, args.timeslot);
+    let mut host = Host {
+        service,
+        timeslot: args.timeslot,
+        fetch: Fetch {
+            entropy: Some(entropy),
+            items: Some(items),
+            ..Default::default()
+        },
+        regular: Some(partial.clone()),
+        exceptional: Some(partial),
+        ..Default::default()
+    };
+
+    let (exec, gas) = self::invoke(
+        &code,
+        (ACCUMULATE_ENTRY, "accumulate"),
+        args.gas,
+        &input,
+        &mut host,
+    )?;
+    let (reason, partial, output) = match exec {
+        WorkExecResult::Ok(output) => (Reason::Halt, host.regular, output),
+        WorkExecResult::OutOfGas => (Reason::OOG, host.exceptional, Vec::new()),
+        other => (
+            Reason::Panic(format!("{other:?}")),
+            host.exceptional,
+            Vec::new(),
+        ),
+    };
+
+    let mut partial = partial.expect("accumulate context");
+    let hash = OpaqueHash::try_from(output).ok().or(partial.yielded);
+
+    // apply the provided preimages
+    for (service, data) in partial.provisions.drain(..) {
+        let Some(account) = partial.state.accounts.get_mut(&service) else {
+            continue;
+        };
+
+        let hash = service::blake2b(&data);
+        let key = (hash, data.len() as u32);
+        if account
+            .lookup
+            .get(&key)
+            .is_some_and(|history| history.is_empty())
+        {
+            account.lookup.insert(key, vec![args.timeslot]);
+            account.preimage.insert(hash, data);
+        }
+    }
+
+    Ok(Accumulated {
+        context: partial.state,
+        reason,
+        gas: gas as _,
+        transfers: partial.transfers,
+        hash,
+    })
+}
+
+/// Wrap the result of an invocation, the output is the data of a success
+fn executed(exec: WorkExecResult, gas: u64) -> Executed {
+    let data = match &exec {
+        WorkExecResult::Ok(output) => output.clone(),
+        _ => Vec::new(),
+    };
+
+    Executed::new(data, exec, gas)
+}
+
+/// Run the program until it exits, returns the result and the gas used
+fn invoke(
+    code: &[u8],
+    (entry, name): (u32, &str),
+    gas: u64,
+    input: &[u8],
+    host: &mut Host,
+) -> Result<(WorkExecResult, u64)> {
+    let Ok(program) = Program::from_service(code) else {
+        return Ok((WorkExecResult::BadCode, 0));
+    };
+
+    let mut vm = Vm::new(&program, entry, gas, input)?;
+    let exit = if trace::enabled() || profile::enabled() {
+        profile::enter(name);
+        self::traced(&mut vm, host)
+    } else {
+        loop {
+            match vm.run() {
+                Exit::HostCall(id) => {
+                    if let Err(exit) = host.call(id, &mut vm) {
+                        break exit;
+                    }
+                }
+                exit => break exit,
+            }
+        }
+    };
+
+    let used = gas.saturating_sub(vm.gas.max(0) as u64);
+    let result = match exit {
+        Exit::Halt => WorkExecResult::Ok(vm.output()),
+        Exit::OutOfGas => WorkExecResult::OutOfGas,
+        Exit::Panic | Exit::Fault(_) | Exit::HostCall(_) => WorkExecResult::Panic,
+    };
+
+    tracing::debug!("exit={exit:?}, gas={used}");
+    Ok((result, used))
+}
+
+/// Run the program step by step, record the steps and profile the gas
+fn traced(vm: &mut Vm, host: &mut Host) -> Exit {
+    let (tracing, profiling) = (trace::enabled(), profile::enabled());
+    loop {
+        let (pc, regs, gas) = (vm.pc, vm.regs, vm.gas);
+        let opcode = vm.program.opcode(pc).unwrap_or_default();
+        let mut exit = vm.step();
+        let mut call = None;
+        if let Some(Exit::HostCall(id)) = exit {
+            let args = vm.regs[7..13].try_into().expect("enough registers");
+            exit = host.call(id, vm).err();
+            call = Some(HostCall {
+                id,
+                args,
+                result: vm.regs[7],
+            });
+        }
+
+        if profiling {
+            let used = gas.saturating_sub(vm.gas.max(0)).max(0) as u64;
+            profile::record(used, call.as_ref().map(|call| call.id), self::flow(vm, pc));
+        }
+
+        if tracing {
+            trace::record(Step::new(pc, opcode, vm.gas, &regs, &vm.regs, call));
+        }
+
+        if let Some(exit) = exit {
+            return exit;
+        }
+    }
+}
+
+/// Get the control flow of the instruction at `pc`
+///
+/// Calls are the jumps which write the return address into `ra`.
+fn flow(vm: &Vm, pc: u32) -> Flow {
+    let program = vm.program;
+    match program.opcode(pc) {
+        Some(80 | 180) if program.args(pc)[0] & 0x0f == 0 => Flow::Call {
+            entry: vm.pc,
+            ret: pc + 1 + program.skip(pc),
+        },
+        Some(50 | 180) => Flow::Jump(vm.pc),
+        _ => Flow::Next,
+    }
+}
@@ -0,0 +1,200 @@
+//! Program blob of the standard program initialization
+
+use anyhow::{Result, anyhow, bail};
+use std::collections::BTreeSet;
+
+/// Opcodes which terminate a basic block
+const TERMINATORS: [u8; 22] = [
+    0, 1, 40, 50, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 170, 171, 172, 173, 174, 175, 180,
+];
+
+/// The jump table alignment
+const JUMP_ALIGNMENT: u32 = 2;
+
+/// A deblobbed PVM program
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    /// The read-only data
+    pub ro_data: Vec<u8>,
+
+    /// The read-write data
+    pub rw_data: Vec<u8>,
+
+    /// The extra pages of the read-write data
+    pub rw_pages: u16,
+
+    /// The stack size
+    pub stack_size: u32,
+
+    /// The instruction code
+    pub code: Vec<u8>,
+
+    /// The instruction bitmask
+    pub bitmask: Vec<bool>,
+
+    /// The jump table
+    pub jump_table: Vec<u32>,
+
+    /// The basic block starts
+    pub blocks: BTreeSet<u32>,
+}
+
+impl Program {
+    /// Parse a service code blob with the conventional metadata prefix
+    pub fn from_service(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader(blob);
+        let len = reader.natural()? as usize;
+        reader.take(len)?;
+        Self::from_standard(reader.0)
+    }
+
+    /// Parse a standard program blob
+    pub fn from_standard(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader(blob);
+        let ro_len = reader.fixed(3)? as usize;
+        let rw_len = reader.fixed(3)? as usize;
+        let rw_pages = reader.fixed(2)? as u16;
+        let stack_size = reader.fixed(3)? as u32;
+        let ro_data = reader.take(ro_len)?.to_vec();
+        let rw_data = reader.take(rw_len)?.to_vec();
+        let code_len = reader.fixed(4)? as usize;
+        let code = reader.take(code_len)?;
+        if !reader.0.is_empty() {
+            bail!("trailing bytes after the program code");
+        }
+
+        let mut program = Self::deblob(code)?;
+        program.ro_data = ro_data;
+        program.rw_data = rw_data;
+        program.rw_pages = rw_pages;
+        program.stack_size = stack_size;
+        Ok(program)
+    }
+
+    /// Split the program code into the jump table, the instructions and
+    /// the bitmask
+    fn deblob(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader(blob);
+        let jump_len = reader.natural()? as usize;
+        let entry_size = reader.fixed(1)? as usize;
+        let code_len = reader.natural()? as usize;
+        if entry_size > 4 {
+            bail!("invalid jump table entry size {entry_size}");
+        }
+
+        let mut jump_table = Vec::with_capacity(jump_len);
+        for _ in 0..jump_len {
+            jump_table.push(reader.fixed(entry_size)? as u32);
+        }
+
+        let code = reader.take(code_len)?.to_vec();
+        let mask = reader.take(code_len.div_ceil(8))?;
+        let bitmask = (0..code_len)
+            .map(|i| mask[i / 8] & (1 << (i % 8)) != 0)
+            .collect::<Vec<_>>();
+
+        let mut program = Self {
+            code,
+            bitmask,
+            jump_table,
+            ..Default::default()
+        };
+        program.blocks = program.basic_blocks();
+        Ok(program)
+    }
+
+    /// Get the opcode at the given counter
+    pub fn opcode(&self, pc: u32) -> Option<u8> {
+        let pc = pc as usize;
+        if !self.bitmask.get(pc).copied().unwrap_or(false) {
+            return None;
+        }
+
+        self.code.get(pc).copied()
+    }
+
+    /// Get the skip length of the instruction at the given counter
+    pub fn skip(&self, pc: u32) -> u32 {
+        let pc = pc as usize;
+        (0..24)
+            .find(|j| self.bitmask.get(pc + 1 + j).copied().unwrap_or(true))
+            .unwrap_or(24) as u32
+    }
+
+    /// Get the code bytes after the opcode, padded with zeros
+    pub fn args(&self, pc: u32) -> [u8; 16] {
+        let mut args = [0; 16];
+        let start = (pc as usize + 1).min(self.code.len());
+        let end = (start + 16).min(self.code.len());
+        args[..end - start].copy_from_slice(&self.code[start..end]);
+        args
+    }
+
+    /// Resolve a dynamic jump address
+    pub fn djump(&self, address: u32) -> Option<u32> {
+        if address == 0 || !address.is_multiple_of(JUMP_ALIGNMENT) {
+            return None;
+        }
+
+        let target = *self
+            .jump_table
+            .get((address / JUMP_ALIGNMENT - 1) as usize)?;
+        self.blocks.contains(&target).then_some(target)
+    }
+
+    /// Collect the basic block starts
+    fn basic_blocks(&self) -> BTreeSet<u32> {
+        let mut blocks = BTreeSet::from([0]);
+        for pc in 0..self.code.len() as u32 {
+            let Some(opcode) = self.opcode(pc) else {
+                continue;
+            };
+
+            if TERMINATORS.contains(&opcode) {
+                let next = pc + 1 + self.skip(pc);
+                if self.opcode(next).is_some() {
+                    blocks.insert(next);
+                }
+            }
+        }
+
+        blocks
+    }
+}
+
+/// A reader of the blob encodings
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    /// Take bytes from the reader
+    fn take(&mut self, len: usize) -> Result<&'b [u8]> {
+        if self.0.len() < len {
+            return Err(anyhow!("unexpected end of program blob"));
+        }
+
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    /// Read a fixed-length little-endian integer
+    fn fixed(&mut self, len: usize) -> Result<u64> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    /// Read a general natural number
+    fn natural(&mut self) -> Result<u64> {
+        let head = self.take(1)?[0];
+        let len = head.leading_ones() as usize;
+        if len == 8 {
+            return self.fixed(8);
+        }
+
+        let high = (head as u64 & ((1 << (7 - len)) - 1)) << (8 * len);
+        Ok(high | self.fixed(len)?)
+    }
+}
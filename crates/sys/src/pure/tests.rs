@@ -0,0 +1,943 @@
+//! Tests of the pure backend
+
+use super::*;
+use crate::{
+    logs,
+    pure::{host::storage_key, memory::Fault},
+};
+use service::{
+    CORES_COUNT, SEGMENT_SIZE, ServiceId,
+    api::{Accounts, AccumulateState, ValidatorData},
+    service::{ServiceAccount, WorkItem, WorkPackage},
+};
+
+/// The address of the read-write data of the test programs
+const RW: u64 = 2 << 16;
+
+/// The address of the read-only data of the test programs
+const RO: u64 = 1 << 16;
+
+/// The gas charged for each host call
+const HOST_CALL_GAS: i64 = 10;
+
+/// The item does not exist
+const NONE: u64 = u64::MAX;
+
+/// The name is unknown
+const WHAT: u64 = u64::MAX - 1;
+
+/// The index is not known
+const WHO: u64 = u64::MAX - 3;
+
+/// The storage is full
+const FULL: u64 = u64::MAX - 4;
+
+/// The core index is not known
+const CORE: u64 = u64::MAX - 5;
+
+/// The balance is insufficient
+const CASH: u64 = u64::MAX - 6;
+
+/// The item is in an unexpected state
+const HUH: u64 = u64::MAX - 8;
+
+/// The accumulating service of the tests
+const SERVICE: ServiceId = 1;
+
+/// `jump_ind ra`, halts the program
+const HALT: &[u8] = &[50, 0];
+
+/// `trap`, panics the program
+const TRAP: &[u8] = &[0];
+
+/// `fallthrough` padded to the accumulate entry point
+const PADDING: &[u8] = &[1, 0, 0, 0, 0];
+
+/// Assemble the code of a program with a 4-byte jump table
+fn code(instructions: &[&[u8]], jumps: &[u32]) -> Vec<u8> {
+    let (mut code, mut mask) = (Vec::new(), Vec::new());
+    for instruction in instructions {
+        mask.push(true);
+        mask.extend(vec![false; instruction.len() - 1]);
+        code.extend_from_slice(instruction);
+    }
+
+    assert!(code.len() < 128 && jumps.len() < 128);
+    let mut blob = vec![jumps.len() as u8, 4, code.len() as u8];
+    jumps
+        .iter()
+        .for_each(|jump| blob.extend(jump.to_le_bytes()));
+    blob.extend(&code);
+    blob.extend(mask.chunks(8).map(|bits| {
+        bits.iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << i))
+    }));
+    blob
+}
+
+/// Assemble a standard program blob with 4 pages of read-write data
+fn blob(instructions: &[&[u8]], ro: &[u8], jumps: &[u32]) -> Vec<u8> {
+    let code = self::code(instructions, jumps);
+    let mut blob = Vec::new();
+    blob.extend(&(ro.len() as u32).to_le_bytes()[..3]);
+    blob.extend([0, 0, 0]);
+    blob.extend(4u16.to_le_bytes());
+    blob.extend(&4096u32.to_le_bytes()[..3]);
+    blob.extend(ro);
+    blob.extend((code.len() as u32).to_le_bytes());
+    blob.extend(code);
+    blob
+}
+
+/// Assemble a service code blob without metadata
+fn service(instructions: &[&[u8]], ro: &[u8]) -> Vec<u8> {
+    let mut blob = vec![0];
+    blob.extend(self::blob(instructions, ro, &[]));
+    blob
+}
+
+/// Parse an assembled program
+fn program(instructions: &[&[u8]]) -> Program {
+    Program::from_standard(&self::blob(instructions, &[], &[])).unwrap()
+}
+
+/// Run an assembled program
+fn run(program: &Program, gas: u64) -> (Exit, Vm<'_>) {
+    let mut vm = Vm::new(program, 0, gas, &[]).unwrap();
+    (vm.run(), vm)
+}
+
+/// Call a host function with the given arguments
+fn call(host: &mut Host, vm: &mut Vm, id: u32, args: &[u64]) -> Result<u64, Exit> {
+    vm.regs[7..7 + args.len()].copy_from_slice(args);
+    host.call(id, vm)?;
+    Ok(vm.regs[7])
+}
+
+/// A 32-bit immediate
+fn imm(value: u32) -> [u8; 4] {
+    value.to_le_bytes()
+}
+
+/// An account running the given code
+fn account(index: ServiceId, code: Vec<u8>) -> ServiceAccount {
+    let mut account = ServiceAccount {
+        index,
+        ..Default::default()
+    };
+    let hash = service::blake2b(&code);
+    account.info.code = hash;
+    account.info.balance = 1_000;
+    account.preimage.insert(hash, code);
+    account
+}
+
+/// An accumulate state with the given accounts
+fn state(accounts: Accounts) -> AccumulateState {
+    let key = ValidatorData {
+        bandersnatch: [0; 32],
+        ed25519: [0; 32],
+        bls: [0; 144],
+        metadata: [0; 128],
+    };
+    AccumulateState {
+        accounts,
+        validators: [key; 6],
+        authorization: Default::default(),
+        privileges: Default::default(),
+        entropy: [[1; 32], [2; 32], [3; 32], [4; 32]],
+    }
+}
+
+/// The host of an accumulation of [`SERVICE`]
+fn accumulating(accounts: Accounts) -> Host {
+    let partial = Partial::new(self::state(accounts), SERVICE, 10);
+    Host {
+        service: SERVICE,
+        timeslot: 10,
+        regular: Some(partial.clone()),
+        exceptional: Some(partial),
+        ..Default::default()
+    }
+}
+
+/// The accounts with [`SERVICE`] only
+fn accounts() -> Accounts {
+    Accounts::from([(SERVICE, self::account(SERVICE, Vec::new()))])
+}
+
+/// The regular partial state of an accumulating host
+fn partial(host: &Host) -> &Partial {
+    host.regular.as_ref().unwrap()
+}
+
+#[test]
+fn decode_standard_program() {
+    let blob = self::blob(&[&[1], &[40, 3], &[0], HALT], b"ro", &[3]);
+    let program = Program::from_standard(&blob).unwrap();
+    assert_eq!(program.ro_data, b"ro");
+    assert_eq!(program.rw_pages, 4);
+    assert_eq!(program.stack_size, 4096);
+    assert_eq!(program.code, [1, 40, 3, 0, 50, 0]);
+    assert_eq!(program.opcode(1), Some(40));
+    assert_eq!(program.opcode(2), None);
+    assert_eq!(program.skip(1), 1);
+    assert_eq!(program.skip(4), 1);
+    assert_eq!(program.args(1)[..2], [3, 0]);
+    assert_eq!(
+        program.blocks.iter().copied().collect::<Vec<_>>(),
+        [0, 1, 3, 4]
+    );
+    assert_eq!(program.djump(2), Some(3));
+    assert_eq!(program.djump(0), None);
+    assert_eq!(program.djump(3), None);
+    assert_eq!(program.djump(4), None);
+}
+
+#[test]
+fn reject_malformed_programs() {
+    let blob = self::blob(&[HALT], &[], &[]);
+    assert!(Program::from_standard(&blob[..blob.len() - 1]).is_err());
+
+    let mut trailing = blob.clone();
+    trailing.push(0);
+    assert!(Program::from_standard(&trailing).is_err());
+
+    let mut code = self::code(&[HALT], &[]);
+    code[1] = 5;
+    let mut oversized = blob[..11].to_vec();
+    oversized.extend((code.len() as u32).to_le_bytes());
+    oversized.extend(code);
+    assert!(Program::from_standard(&oversized).is_err());
+
+    let mut service = vec![0x81, 0x00];
+    service.extend(vec![0; 0x100]);
+    service.extend(&blob);
+    assert!(Program::from_service(&service).is_ok());
+    assert!(Program::from_service(&service[..0x80]).is_err());
+}
+
+#[test]
+fn execute_arithmetic() {
+    let program = self::program(&[
+        &[51, 7, 6],
+        &[51, 8, 7],
+        &[200, 7 | 8 << 4, 9],
+        &[149, 10 | 9 << 4, 0xff],
+        &[51, 11, 0],
+        &[203, 9 | 11 << 4, 12],
+        &[20, 2, 1, 2, 3, 4, 5, 6, 7, 8],
+        HALT,
+    ]);
+    let (exit, vm) = self::run(&program, 100);
+    assert_eq!(exit, Exit::Halt);
+    assert_eq!(vm.regs[9], 13);
+    assert_eq!(vm.regs[10], 12);
+    assert_eq!(vm.regs[12], u64::MAX);
+    assert_eq!(vm.regs[2], 0x0807060504030201);
+}
+
+#[test]
+fn sign_extend_immediates() {
+    let program = self::program(&[&[51, 7, 0xff], &[51, 8, 0xff, 0x7f], HALT]);
+    let (exit, vm) = self::run(&program, 100);
+    assert_eq!(exit, Exit::Halt);
+    assert_eq!(vm.regs[7], u64::MAX);
+    assert_eq!(vm.regs[8], 0x7fff);
+}
+
+#[test]
+fn execute_branches() {
+    // branch_eq_imm r9, 0, +5 skips the trap
+    let program = self::program(&[&[81, 9 | 1 << 4, 0, 5], TRAP, HALT]);
+    assert_eq!(self::run(&program, 100).0, Exit::Halt);
+
+    // branch_eq_imm r9, 1, +5 falls through to the trap
+    let program = self::program(&[&[81, 9 | 1 << 4, 1, 5], TRAP, HALT]);
+    assert_eq!(self::run(&program, 100).0, Exit::Panic);
+
+    // the target is not the start of a basic block
+    let program = self::program(&[&[40, 1], HALT]);
+    assert_eq!(self::run(&program, 100).0, Exit::Panic);
+
+    // jump_ind through the jump table
+    let blob = self::blob(&[&[51, 7, 2], &[50, 7], TRAP, HALT], &[], &[6]);
+    let program = Program::from_standard(&blob).unwrap();
+    assert_eq!(self::run(&program, 100).0, Exit::Halt);
+}
+
+#[test]
+fn panic_on_invalid_instructions() {
+    assert_eq!(self::run(&self::program(&[TRAP]), 100).0, Exit::Panic);
+    assert_eq!(self::run(&self::program(&[&[255]]), 100).0, Exit::Panic);
+    assert_eq!(self::run(&self::program(&[&[1]]), 100).0, Exit::Panic);
+
+    // unaligned dynamic jump
+    let program = self::program(&[&[51, 7, 3], &[50, 7]]);
+    assert_eq!(self::run(&program, 100).0, Exit::Panic);
+}
+
+#[test]
+fn charge_gas_per_instruction() {
+    let program = self::program(&[&[1], &[1], &[1], HALT]);
+    let (exit, vm) = self::run(&program, 4);
+    assert_eq!((exit, vm.gas), (Exit::Halt, 0));
+
+    let (exit, vm) = self::run(&program, 3);
+    assert_eq!(exit, Exit::OutOfGas);
+    assert!(vm.gas < 0);
+
+    let mut vm = Vm::new(&program, 0, 5, &[]).unwrap();
+    assert!(vm.charge(5));
+    assert!(!vm.charge(1));
+}
+
+#[test]
+fn charge_gas_per_host_call() {
+    let program = self::program(&[&[10, 0], HALT]);
+    let mut vm = Vm::new(&program, 0, 100, &[]).unwrap();
+    assert_eq!(vm.run(), Exit::HostCall(0));
+
+    let mut host = Host::default();
+    host.call(0, &mut vm).unwrap();
+    assert_eq!(vm.gas, 99 - HOST_CALL_GAS);
+    assert_eq!(vm.regs[7], (99 - HOST_CALL_GAS) as u64);
+
+    vm.gas = HOST_CALL_GAS - 1;
+    assert_eq!(host.call(0, &mut vm), Err(Exit::OutOfGas));
+}
+
+#[test]
+fn load_and_store() {
+    let address = imm(RW as u32);
+    let program = self::program(&[
+        &[51, 7, 0x2a],
+        &[62, 7, address[0], address[1], address[2], address[3]],
+        &[58, 8, address[0], address[1], address[2], address[3]],
+        &[51, 9, address[0], address[1], address[2], address[3]],
+        &[120, 7 | 9 << 4, 8],
+        &[125, 10 | 9 << 4, 8],
+        HALT,
+    ]);
+    let (exit, vm) = self::run(&program, 100);
+    assert_eq!(exit, Exit::Halt);
+    assert_eq!(vm.regs[8], 0x2a);
+    assert_eq!(vm.regs[10], 0x2a);
+    assert_eq!(vm.memory.read_int(RW, 8), Ok(0x2a));
+}
+
+#[test]
+fn fault_on_inaccessible_memory() {
+    // load from the unmapped zone below the read-only data
+    let program = self::program(&[&[52, 7, 0x10], HALT]);
+    assert_eq!(self::run(&program, 100).0, Exit::Fault(0x10));
+
+    // store to the read-only data
+    let blob = self::blob(&[&[59, 7, 0, 0, 1, 0], HALT], b"ro", &[]);
+    let program = Program::from_standard(&blob).unwrap();
+    assert_eq!(self::run(&program, 100).0, Exit::Fault(RO));
+
+    // the memory is untouched by a write across the end of the mapping
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 100, &[]).unwrap();
+    let end = RW + 4 * 4096;
+    assert_eq!(vm.memory.write(end - 2, &[1; 4]), Err(Fault(end)));
+    assert_eq!(vm.memory.read_int(end - 2, 2), Ok(0));
+    assert!(!vm.memory.is_writable(end - 2, 4));
+    assert!(vm.memory.read_vec(RW, 1 << 30).is_err());
+    assert_eq!(vm.memory.read_int(1 << 32, 1), Err(Fault(1 << 32)));
+}
+
+#[test]
+fn grow_the_heap() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 100, &[]).unwrap();
+    let top = vm.memory.sbrk(0).unwrap() as u64;
+    assert_eq!(top, RW + 4 * 4096);
+    assert!(!vm.memory.is_writable(top, 1));
+
+    assert_eq!(vm.memory.sbrk(10), Some(top as u32));
+    assert!(vm.memory.is_writable(top, 4096));
+    assert_eq!(vm.memory.sbrk(0), Some(top as u32 + 10));
+    assert_eq!(vm.memory.sbrk(1 << 32), None);
+}
+
+#[test]
+fn host_fetch() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = Host {
+        fetch: Fetch {
+            auth_output: Some(b"output".to_vec()),
+            imports: vec![vec![Segment([7; SEGMENT_SIZE])]],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let constants = host.constants.encode();
+    let len = self::call(&mut host, &mut vm, 1, &[RW, 0, 1024, 0, 0, 0]).unwrap();
+    assert_eq!(len, constants.len() as u64);
+    assert_eq!(vm.memory.read_vec(RW, len).unwrap(), constants);
+
+    assert_eq!(
+        self::call(&mut host, &mut vm, 1, &[RW, 2, 3, 2, 0, 0]),
+        Ok(6)
+    );
+    assert_eq!(vm.memory.read_vec(RW, 3).unwrap(), b"tpu");
+
+    let len = self::call(&mut host, &mut vm, 1, &[RW, 0, 8, 5, 0, 0]).unwrap();
+    assert_eq!(len, SEGMENT_SIZE as u64);
+    assert_eq!(vm.memory.read_vec(RW, 8).unwrap(), [7; 8]);
+
+    assert_eq!(
+        self::call(&mut host, &mut vm, 1, &[RW, 0, 8, 1, 0, 0]),
+        Ok(NONE)
+    );
+    assert_eq!(
+        self::call(&mut host, &mut vm, 1, &[RW, 0, 8, 5, 1, 0]),
+        Ok(NONE)
+    );
+    assert_eq!(
+        self::call(&mut host, &mut vm, 1, &[0x10, 0, 8, 2, 0, 0]),
+        Err(Exit::Fault(0x10))
+    );
+}
+
+#[test]
+fn host_lookup_and_read() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut account = self::account(SERVICE, b"code".to_vec());
+    let hash = account.info.code;
+    account
+        .storage
+        .insert(storage_key(SERVICE, b"key").into(), b"value".to_vec());
+    let mut host = Host {
+        service: SERVICE,
+        accounts: Accounts::from([(SERVICE, account)]),
+        ..Default::default()
+    };
+
+    vm.memory.write(RW, &hash).unwrap();
+    let out = RW + 64;
+    assert_eq!(
+        self::call(&mut host, &mut vm, 2, &[u64::MAX, RW, out, 0, 4]),
+        Ok(4)
+    );
+    assert_eq!(vm.memory.read_vec(out, 4).unwrap(), b"code");
+    assert_eq!(
+        self::call(&mut host, &mut vm, 2, &[2, RW, out, 0, 4]),
+        Ok(NONE)
+    );
+
+    vm.memory.write(RW, b"key").unwrap();
+    let args = [u64::MAX, RW, 3, out, 1, 3];
+    assert_eq!(self::call(&mut host, &mut vm, 3, &args), Ok(5));
+    assert_eq!(vm.memory.read_vec(out, 3).unwrap(), b"alu");
+    assert_eq!(
+        self::call(&mut host, &mut vm, 3, &[u64::MAX, RW, 2, out, 0, 5]),
+        Ok(NONE)
+    );
+
+    // a null output pointer allocates the value on the heap
+    let top = vm.memory.sbrk(0).unwrap() as u64;
+    assert_eq!(
+        self::call(&mut host, &mut vm, 3, &[u64::MAX, RW, 3, 0, 0, 0]),
+        Ok(5)
+    );
+    assert_eq!(
+        self::call(&mut host, &mut vm, 3, &[u64::MAX, RW, 3, 0, 0, 5]),
+        Ok(top)
+    );
+    assert_eq!(vm.memory.read_vec(top, 5).unwrap(), b"value");
+}
+
+#[test]
+fn host_write() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    vm.memory.write(RW, b"keyvalue").unwrap();
+
+    // not available out of accumulate
+    let mut host = Host::default();
+    assert_eq!(
+        self::call(&mut host, &mut vm, 4, &[RW, 3, RW + 3, 5]),
+        Ok(WHAT)
+    );
+
+    let mut host = self::accumulating(self::accounts());
+    let key = storage_key(SERVICE, b"key");
+    assert_eq!(
+        self::call(&mut host, &mut vm, 4, &[RW, 3, RW + 3, 5]),
+        Ok(NONE)
+    );
+    let storage = &self::partial(&host).state.accounts[&SERVICE].storage;
+    assert_eq!(storage.get(key.as_ref()), Some(&b"value".to_vec()));
+
+    assert_eq!(self::call(&mut host, &mut vm, 4, &[RW, 3, RW, 0]), Ok(5));
+    let storage = &self::partial(&host).state.accounts[&SERVICE].storage;
+    assert!(storage.is_empty());
+    assert_eq!(
+        self::call(&mut host, &mut vm, 4, &[0x10, 3, RW, 0]),
+        Err(Exit::Fault(0x10))
+    );
+}
+
+#[test]
+fn host_info_and_historical_lookup() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut account = self::account(SERVICE, b"code".to_vec());
+    let hash = account.info.code;
+    account.lookup.insert((hash, 4), vec![5]);
+    let info = codec::encode(&account.info).unwrap();
+    let mut host = Host {
+        service: SERVICE,
+        timeslot: 4,
+        accounts: Accounts::from([(SERVICE, account)]),
+        exports: Some(Vec::new()),
+        ..Default::default()
+    };
+
+    let len = self::call(&mut host, &mut vm, 5, &[u64::MAX, RW, 0, 1024]).unwrap();
+    assert_eq!(vm.memory.read_vec(RW, len).unwrap(), info);
+    assert_eq!(
+        self::call(&mut host, &mut vm, 5, &[2, RW, 0, 1024]),
+        Ok(NONE)
+    );
+
+    vm.memory.write(RW, &hash).unwrap();
+    let args = [u64::MAX, RW, RW + 64, 0, 4];
+    assert_eq!(self::call(&mut host, &mut vm, 6, &args), Ok(NONE));
+    host.timeslot = 5;
+    assert_eq!(self::call(&mut host, &mut vm, 6, &args), Ok(4));
+    assert_eq!(vm.memory.read_vec(RW + 64, 4).unwrap(), b"code");
+}
+
+#[test]
+fn host_export() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    vm.memory.write(RW, b"segment").unwrap();
+    let mut host = Host {
+        export_offset: 2,
+        exports: Some(Vec::new()),
+        ..Default::default()
+    };
+    host.constants.max_exports = 4;
+
+    assert_eq!(self::call(&mut host, &mut vm, 7, &[RW, 7]), Ok(2));
+    assert_eq!(self::call(&mut host, &mut vm, 7, &[RW, 3]), Ok(3));
+    assert_eq!(self::call(&mut host, &mut vm, 7, &[RW, 3]), Ok(FULL));
+
+    let exports = host.exports.unwrap();
+    assert_eq!(exports.len(), 2);
+    assert_eq!(&exports[0].0[..8], b"segment\0");
+    assert_eq!(&exports[1].0[..4], b"seg\0");
+
+    // not available out of refine
+    let mut host = Host::default();
+    assert_eq!(self::call(&mut host, &mut vm, 7, &[RW, 3]), Ok(WHAT));
+}
+
+#[test]
+fn host_bless() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    host.regular.as_mut().unwrap().state.privileges.bless = SERVICE;
+
+    let assign = (0..CORES_COUNT as u32)
+        .flat_map(|core| (10 + core).to_le_bytes())
+        .collect::<Vec<_>>();
+    vm.memory.write(RW, &assign).unwrap();
+    let mut always = 7u32.to_le_bytes().to_vec();
+    always.extend(100u64.to_le_bytes());
+    vm.memory.write(RW + 64, &always).unwrap();
+
+    let args = [2, RW, 3, 4, RW + 64, 1];
+    assert_eq!(self::call(&mut host, &mut vm, 14, &args), Ok(0));
+    let privileges = &self::partial(&host).state.privileges;
+    assert_eq!(privileges.bless, 2);
+    assert_eq!(privileges.assign[0], 10);
+    assert_eq!(privileges.assign[CORES_COUNT - 1], 9 + CORES_COUNT as u32);
+    assert_eq!(privileges.designate, 3);
+    assert_eq!(privileges.register, 4);
+    assert_eq!(privileges.always_acc.get(&7), Some(&100));
+
+    // the service is not the manager anymore
+    assert_eq!(self::call(&mut host, &mut vm, 14, &args), Ok(HUH));
+    let args = [1 << 32, RW, 3, 4, RW + 64, 1];
+    assert_eq!(self::call(&mut host, &mut vm, 14, &args), Ok(WHO));
+}
+
+#[test]
+fn host_assign() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    host.regular.as_mut().unwrap().state.privileges.assign[1] = SERVICE;
+    let size = host.constants.auth_queue as usize;
+    vm.memory.write(RW, &vec![9; 32 * size]).unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 15, &[0, RW, 2]), Ok(HUH));
+    let core = CORES_COUNT as u64;
+    assert_eq!(self::call(&mut host, &mut vm, 15, &[core, RW, 2]), Ok(CORE));
+    assert_eq!(
+        self::call(&mut host, &mut vm, 15, &[1, RW, 1 << 32]),
+        Ok(WHO)
+    );
+    assert_eq!(self::call(&mut host, &mut vm, 15, &[1, RW, 2]), Ok(0));
+
+    let state = &self::partial(&host).state;
+    assert_eq!(state.authorization[1], vec![[9; 32]; size]);
+    assert!(state.authorization[0].is_empty());
+    assert_eq!(state.privileges.assign[1], 2);
+}
+
+#[test]
+fn host_designate() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 10_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    vm.memory.write(RW, &[5; 336 * 6]).unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 16, &[RW]), Ok(HUH));
+    host.regular.as_mut().unwrap().state.privileges.designate = SERVICE;
+    assert_eq!(self::call(&mut host, &mut vm, 16, &[RW]), Ok(0));
+
+    let validators = &self::partial(&host).state.validators;
+    assert!(validators.iter().all(|key| key.bandersnatch == [5; 32]));
+    assert!(validators.iter().all(|key| key.metadata == [5; 128]));
+}
+
+#[test]
+fn host_checkpoint_and_yield() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    vm.memory.write(RW, &[3; 32]).unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 25, &[RW]), Ok(0));
+    assert_eq!(self::partial(&host).yielded, Some([3; 32]));
+    assert_eq!(host.exceptional.as_ref().unwrap().yielded, None);
+
+    let gas = self::call(&mut host, &mut vm, 17, &[]).unwrap();
+    assert_eq!(gas, vm.gas as u64);
+    assert_eq!(host.exceptional.as_ref().unwrap().yielded, Some([3; 32]));
+}
+
+#[test]
+fn host_new_and_upgrade() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    vm.memory.write(RW, &[8; 32]).unwrap();
+
+    let next = self::partial(&host).next;
+    assert_eq!(
+        self::call(&mut host, &mut vm, 18, &[RW, 100]),
+        Ok(next as u64)
+    );
+    let partial = self::partial(&host);
+    let account = &partial.state.accounts[&next];
+    assert_eq!(account.info.code, [8; 32]);
+    assert_eq!(account.info.creation, 10);
+    assert_eq!(account.lookup.get(&([8; 32], 100)), Some(&Vec::new()));
+    assert!(next >= 1 << 16);
+    assert_ne!(partial.next, next);
+
+    assert_eq!(self::call(&mut host, &mut vm, 18, &[RW, 1 << 32]), Ok(HUH));
+    assert_eq!(self::call(&mut host, &mut vm, 19, &[RW]), Ok(0));
+    let account = &self::partial(&host).state.accounts[&SERVICE];
+    assert_eq!(account.info.code, [8; 32]);
+}
+
+#[test]
+fn host_transfer() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut accounts = self::accounts();
+    accounts.insert(2, self::account(2, Vec::new()));
+    let mut host = self::accumulating(accounts);
+    vm.memory.write(RW, b"memo").unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 20, &[3, 10, 5, RW]), Ok(WHO));
+    assert_eq!(
+        self::call(&mut host, &mut vm, 20, &[2, 10_000, 5, RW]),
+        Ok(CASH)
+    );
+
+    let gas = vm.gas;
+    assert_eq!(self::call(&mut host, &mut vm, 20, &[2, 10, 5, RW]), Ok(0));
+    assert_eq!(vm.gas, gas - HOST_CALL_GAS - 5);
+
+    let partial = self::partial(&host);
+    assert_eq!(partial.state.accounts[&SERVICE].info.balance, 990);
+    let [transfer] = partial.transfers.as_slice() else {
+        panic!("expected one transfer");
+    };
+    assert_eq!((transfer.sender, transfer.recipient), (SERVICE, 2));
+    assert_eq!((transfer.amount, transfer.gas_limit), (10, 5));
+    assert_eq!(&transfer.memo[..4], b"memo");
+    assert_eq!(transfer.memo.len(), host.constants.memo_size as usize);
+
+    // the gas limit of the transfer is charged
+    let args = [2, 10, vm.gas as u64, RW];
+    assert_eq!(
+        self::call(&mut host, &mut vm, 20, &args),
+        Err(Exit::OutOfGas)
+    );
+}
+
+#[test]
+fn host_eject() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut marker = OpaqueHash::default();
+    marker[..4].copy_from_slice(&SERVICE.to_le_bytes());
+    let mut target = self::account(2, Vec::new());
+    target.info.code = marker;
+    target.lookup.insert(([6; 32], 10), vec![0, 1]);
+    let mut accounts = self::accounts();
+    accounts.insert(2, target);
+    let mut host = self::accumulating(accounts);
+    host.constants.expunge_period = 32;
+    vm.memory.write(RW, &[6; 32]).unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 21, &[3, RW]), Ok(WHO));
+    assert_eq!(
+        self::call(&mut host, &mut vm, 21, &[SERVICE as u64, RW]),
+        Ok(WHO)
+    );
+    assert_eq!(self::call(&mut host, &mut vm, 21, &[2, RW]), Ok(HUH));
+
+    host.timeslot = 100;
+    assert_eq!(self::call(&mut host, &mut vm, 21, &[2, RW]), Ok(0));
+    let accounts = &self::partial(&host).state.accounts;
+    assert!(!accounts.contains_key(&2));
+    assert_eq!(accounts[&SERVICE].info.balance, 2_000);
+}
+
+#[test]
+fn host_preimage_requests() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = self::accumulating(self::accounts());
+    vm.memory.write(RW, &[4; 32]).unwrap();
+
+    // query, solicit and forget a preimage
+    assert_eq!(self::call(&mut host, &mut vm, 22, &[RW, 8]), Ok(NONE));
+    assert_eq!(self::call(&mut host, &mut vm, 23, &[RW, 8]), Ok(0));
+    assert_eq!(self::call(&mut host, &mut vm, 22, &[RW, 8]), Ok(0));
+    assert_eq!(self::call(&mut host, &mut vm, 23, &[RW, 8]), Ok(HUH));
+    assert_eq!(self::call(&mut host, &mut vm, 24, &[RW, 8]), Ok(0));
+    assert_eq!(self::call(&mut host, &mut vm, 22, &[RW, 8]), Ok(NONE));
+    assert_eq!(self::call(&mut host, &mut vm, 24, &[RW, 8]), Ok(HUH));
+
+    // forget an available preimage
+    let account = host
+        .regular
+        .as_mut()
+        .unwrap()
+        .state
+        .accounts
+        .get_mut(&SERVICE);
+    account.unwrap().lookup.insert(([4; 32], 8), vec![3]);
+    assert_eq!(
+        self::call(&mut host, &mut vm, 22, &[RW, 8]),
+        Ok(1 + (3 << 32))
+    );
+    assert_eq!(self::call(&mut host, &mut vm, 24, &[RW, 8]), Ok(0));
+    assert_eq!(
+        self::call(&mut host, &mut vm, 22, &[RW, 8]),
+        Ok(2 + (3 << 32))
+    );
+    assert_eq!(vm.regs[8], 10);
+}
+
+#[test]
+fn host_provide() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut accounts = self::accounts();
+    let hash = service::blake2b(b"data");
+    accounts
+        .get_mut(&SERVICE)
+        .unwrap()
+        .lookup
+        .insert((hash, 4), Vec::new());
+    let mut host = self::accumulating(accounts);
+    vm.memory.write(RW, b"data").unwrap();
+
+    assert_eq!(self::call(&mut host, &mut vm, 26, &[3, RW, 4]), Ok(WHO));
+    assert_eq!(
+        self::call(&mut host, &mut vm, 26, &[u64::MAX, RW, 3]),
+        Ok(HUH)
+    );
+    assert_eq!(
+        self::call(&mut host, &mut vm, 26, &[u64::MAX, RW, 4]),
+        Ok(0)
+    );
+    assert_eq!(
+        self::call(&mut host, &mut vm, 26, &[u64::MAX, RW, 4]),
+        Ok(HUH)
+    );
+    assert_eq!(
+        self::partial(&host).provisions,
+        vec![(SERVICE, b"data".to_vec())]
+    );
+}
+
+#[test]
+fn host_log() {
+    let program = self::program(&[HALT]);
+    let mut vm = Vm::new(&program, 0, 1_000, &[]).unwrap();
+    let mut host = Host {
+        service: SERVICE,
+        ..Default::default()
+    };
+    vm.memory.write(RW, b"targetmessage").unwrap();
+
+    logs::start();
+    self::call(&mut host, &mut vm, 100, &[2, RW, 6, RW + 6, 7]).unwrap();
+    self::call(&mut host, &mut vm, 100, &[0, 0, 0, RW + 6, 7]).unwrap();
+    let logs = logs::stop();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].to_string(), "INFO service=1 target: message");
+    assert_eq!(logs[1].to_string(), "ERROR service=1: message");
+    assert_eq!(self::call(&mut host, &mut vm, 99, &[]), Ok(WHAT));
+}
+
+#[test]
+fn invoke_authorize() {
+    let code = self::service(&[HALT], &[]);
+    let hash = service::blake2b(&code);
+    let args = AuthorizeArgs {
+        package: WorkPackage {
+            auth_code_host: SERVICE,
+            auth_code_hash: hash,
+            ..Default::default()
+        },
+        core_idx: 1,
+        accounts: Accounts::from([(SERVICE, self::account(SERVICE, code))]),
+        timeslot: 0,
+    };
+
+    // the recompiler is not provided
+    let error = self::authorize_with(Backend::Compiler, args.clone()).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Unsupported { .. })
+    ));
+    assert_eq!(Backend::DEFAULT, Backend::Interpreter);
+
+    let executed = self::authorize_with(Backend::Interpreter, args).unwrap();
+    assert_eq!(executed.data, codec::encode(&1u16).unwrap());
+    assert_eq!(executed.exec, WorkExecResult::Ok(executed.data.clone()));
+    assert_eq!(executed.gas, 1);
+
+    let args = AuthorizeArgs {
+        package: Default::default(),
+        core_idx: 0,
+        accounts: Default::default(),
+        timeslot: 0,
+    };
+    assert!(self::authorize(args).is_err());
+}
+
+#[test]
+fn invoke_refine() {
+    let refine = |code: Vec<u8>, export_count: u16| {
+        let item = WorkItem {
+            service: SERVICE,
+            code_hash: service::blake2b(&code),
+            refine_gas_limit: 1_000,
+            accumulate_gas_limit: 0,
+            export_count,
+            payload: b"payload".to_vec(),
+            import_segments: Vec::new(),
+            extrinsic: Vec::new(),
+        };
+        self::refine(RefineArgs {
+            core: 0,
+            index: 0,
+            package: WorkPackage {
+                items: vec![item],
+                ..Default::default()
+            },
+            auth_output: Vec::new(),
+            all_imports: vec![Vec::new()],
+            export_offset: 0,
+            accounts: Accounts::from([(SERVICE, self::account(SERVICE, code))]),
+            timeslot: 0,
+        })
+        .unwrap()
+    };
+
+    // export the refine arguments as a segment
+    let code = self::service(&[&[10, 7], HALT], &[]);
+    let refined = refine(code.clone(), 1);
+    assert!(refined.executed.is_ok());
+    assert_eq!(refined.executed.gas, 2 + HOST_CALL_GAS as u64);
+    assert_eq!(refined.segments.len(), 1);
+    assert_eq!(refined.segments[0].0[0], 0);
+
+    let refined = refine(code, 2);
+    assert_eq!(refined.executed.exec, WorkExecResult::InvalidExports);
+    assert!(refined.executed.data.is_empty());
+    assert!(refined.segments.is_empty());
+
+    let refined = refine(vec![1, 2, 3], 0);
+    assert_eq!(refined.executed.exec, WorkExecResult::BadCode);
+}
+
+#[test]
+fn invoke_accumulate() {
+    // write `key` => `value` from the read-only data
+    let ro = imm(RO as u32);
+    let value = imm(RO as u32 + 3);
+    let write: &[&[u8]] = &[
+        &[51, 7, ro[0], ro[1], ro[2], ro[3]],
+        &[51, 8, 3],
+        &[51, 9, value[0], value[1], value[2], value[3]],
+        &[51, 10, 5],
+        &[10, 4],
+    ];
+    let accumulate = |tail: &[&[u8]], gas: u64| {
+        let code = self::service(&[&[PADDING], write, tail].concat(), b"keyvalue");
+        let accounts = Accounts::from([(SERVICE, self::account(SERVICE, code))]);
+        self::accumulate(AccumulateArgs {
+            context: self::state(accounts),
+            timeslot: 10,
+            service: SERVICE,
+            gas,
+            operands: Vec::new(),
+        })
+        .unwrap()
+    };
+
+    let key = storage_key(SERVICE, b"key");
+
+    let accumulated = accumulate(&[HALT], 1_000);
+    assert_eq!(accumulated.reason, Reason::Halt);
+    assert_eq!(accumulated.hash, None);
+    let storage = &accumulated.context.accounts[&SERVICE].storage;
+    assert_eq!(storage.get(key.as_ref()), Some(&b"value".to_vec()));
+
+    // the writes are reverted on panic
+    let accumulated = accumulate(&[TRAP], 1_000);
+    assert!(matches!(accumulated.reason, Reason::Panic(_)));
+    assert!(accumulated.context.accounts[&SERVICE].storage.is_empty());
+
+    // and kept after a checkpoint
+    let accumulated = accumulate(&[&[10, 17], TRAP], 1_000);
+    let storage = &accumulated.context.accounts[&SERVICE].storage;
+    assert!(storage.contains_key(key.as_ref()));
+
+    let accumulated = accumulate(&[HALT], 4);
+    assert_eq!(accumulated.reason, Reason::OOG);
+    assert_eq!(accumulated.gas, 4);
+}
@@ -0,0 +1,468 @@
+//! Interpreter of the PVM instructions
+
+use crate::pure::{
+    memory::{ARGS_ADDRESS, Fault, Memory, STACK_ADDRESS},
+    program::Program,
+};
+use anyhow::Result;
+
+/// Jumping to this address halts the program
+pub const HALT_ADDRESS: u64 = (1 << 32) - (1 << 16);
+
+/// The number of registers
+pub const REGISTERS: usize = 13;
+
+/// Exit reason of the interpreter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// The program halted
+    Halt,
+
+    /// The program panicked
+    Panic,
+
+    /// The program ran out of gas
+    OutOfGas,
+
+    /// The program accessed inaccessible memory
+    Fault(u64),
+
+    /// The program requested a host call
+    HostCall(u32),
+}
+
+impl From<Fault> for Exit {
+    fn from(fault: Fault) -> Self {
+        Exit::Fault(fault.0)
+    }
+}
+
+/// State of a PVM instance
+#[derive(Debug, Clone)]
+pub struct Vm<'p> {
+    /// The program
+    pub program: &'p Program,
+
+    /// The registers
+    pub regs: [u64; REGISTERS],
+
+    /// The program counter
+    pub pc: u32,
+
+    /// The remaining gas
+    pub gas: i64,
+
+    /// The memory
+    pub memory: Memory,
+}
+
+impl<'p> Vm<'p> {
+    /// Create an instance of the standard program
+    pub fn new(program: &'p Program, pc: u32, gas: u64, args: &[u8]) -> Result<Self> {
+        let mut regs = [0; REGISTERS];
+        regs[0] = HALT_ADDRESS;
+        regs[1] = STACK_ADDRESS as u64;
+        regs[7] = ARGS_ADDRESS as u64;
+        regs[8] = args.len() as u64;
+
+        Ok(Self {
+            program,
+            regs,
+            pc,
+            gas: gas.min(i64::MAX as u64) as i64,
+            memory: Memory::standard(program, args)?,
+        })
+    }
+
+    /// Run the program until it exits
+    pub fn run(&mut self) -> Exit {
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
+        }
+    }
+
+    /// Get the output of a halted program
+    pub fn output(&self) -> Vec<u8> {
+        self.memory
+            .read_vec(self.regs[7], self.regs[8])
+            .unwrap_or_default()
+    }
+
+    /// Charge gas from the instance, returns false if it runs out of gas
+    pub fn charge(&mut self, gas: u64) -> bool {
+        self.gas = self.gas.saturating_sub(gas.min(i64::MAX as u64) as i64);
+        self.gas >= 0
+    }
+
+    /// Execute a single instruction
+    pub fn step(&mut self) -> Option<Exit> {
+        let pc = self.pc;
+        let Some(opcode) = self.program.opcode(pc) else {
+            return Some(Exit::Panic);
+        };
+
+        if !self.charge(1) {
+            return Some(Exit::OutOfGas);
+        }
+
+        let skip = self.program.skip(pc) as usize;
+        let next = pc + 1 + skip as u32;
+        let args = self.program.args(pc);
+        self.pc = next;
+
+        let result = match opcode {
+            0 => Err(Exit::Panic),
+            1 => Ok(()),
+            10 => {
+                let id = imm(&args, skip.min(4));
+                return Some(Exit::HostCall(id as u32));
+            }
+            20 => {
+                let ra = reg(args[0]);
+                let mut value = [0; 8];
+                value.copy_from_slice(&args[1..9]);
+                self.regs[ra] = u64::from_le_bytes(value);
+                Ok(())
+            }
+            30..=33 => {
+                let lx = (args[0] as usize % 8).min(4);
+                let ly = skip.saturating_sub(lx + 1).min(4);
+                let (vx, vy) = (imm(&args[1..], lx), imm(&args[1 + lx..], ly));
+                self.store(vx, vy, width(opcode - 30))
+            }
+            40 => {
+                let target = offset(pc, imm(&args, skip.min(4)));
+                self.branch(target, true)
+            }
+            50..=62 => {
+                let ra = reg(args[0]);
+                let vx = imm(&args[1..], skip.saturating_sub(1).min(4));
+                self.one_reg_one_imm(opcode, ra, vx)
+            }
+            70..=73 => {
+                let ra = reg(args[0]);
+                let lx = ((args[0] >> 4) as usize % 8).min(4);
+                let ly = skip.saturating_sub(lx + 1).min(4);
+                let (vx, vy) = (imm(&args[1..], lx), imm(&args[1 + lx..], ly));
+                self.store(self.regs[ra].wrapping_add(vx), vy, width(opcode - 70))
+            }
+            80..=90 => {
+                let ra = reg(args[0]);
+                let lx = ((args[0] >> 4) as usize % 8).min(4);
+                let ly = skip.saturating_sub(lx + 1).min(4);
+                let vx = imm(&args[1..], lx);
+                let target = offset(pc, imm(&args[1 + lx..], ly));
+                self.one_reg_imm_offset(opcode, ra, vx, target)
+            }
+            100..=111 => {
+                let (rd, ra) = (reg(args[0]), reg(args[0] >> 4));
+                self.two_regs(opcode, rd, ra)
+            }
+            120..=161 => {
+                let (ra, rb) = (reg(args[0]), reg(args[0] >> 4));
+                let vx = imm(&args[1..], skip.saturating_sub(1).min(4));
+                self.two_regs_one_imm(opcode, ra, rb, vx)
+            }
+            170..=175 => {
+                let (ra, rb) = (reg(args[0]), reg(args[0] >> 4));
+                let target = offset(pc, imm(&args[1..], skip.saturating_sub(1).min(4)));
+                let (a, b) = (self.regs[ra], self.regs[rb]);
+                let condition = match opcode {
+                    170 => a == b,
+                    171 => a != b,
+                    172 => a < b,
+                    173 => (a as i64) < (b as i64),
+                    174 => a >= b,
+                    _ => (a as i64) >= (b as i64),
+                };
+                self.branch(target, condition)
+            }
+            180 => {
+                let (ra, rb) = (reg(args[0]), reg(args[0] >> 4));
+                let lx = (args[1] as usize % 8).min(4);
+                let ly = skip.saturating_sub(lx + 2).min(4);
+                let (vx, vy) = (imm(&args[2..], lx), imm(&args[2 + lx..], ly));
+                let target = self.regs[rb].wrapping_add(vy);
+                self.regs[ra] = vx;
+                self.djump(target)
+            }
+            190..=230 => {
+                let (ra, rb, rd) = (reg(args[0]), reg(args[0] >> 4), reg(args[1]));
+                self.three_regs(opcode, ra, rb, rd);
+                Ok(())
+            }
+            _ => Err(Exit::Panic),
+        };
+
+        result.err()
+    }
+
+    /// Instructions with one register and one immediate
+    fn one_reg_one_imm(&mut self, opcode: u8, ra: usize, vx: u64) -> Result<(), Exit> {
+        match opcode {
+            50 => return self.djump(self.regs[ra].wrapping_add(vx)),
+            51 => self.regs[ra] = vx,
+            52..=58 => {
+                let (len, signed) = match opcode {
+                    52 => (1, false),
+                    53 => (1, true),
+                    54 => (2, false),
+                    55 => (2, true),
+                    56 => (4, false),
+                    57 => (4, true),
+                    _ => (8, false),
+                };
+                self.regs[ra] = self.load(vx, len, signed)?;
+            }
+            _ => self.store(vx, self.regs[ra], width(opcode - 59))?,
+        }
+
+        Ok(())
+    }
+
+    /// Instructions with one register, one immediate and one offset
+    fn one_reg_imm_offset(
+        &mut self,
+        opcode: u8,
+        ra: usize,
+        vx: u64,
+        target: u32,
+    ) -> Result<(), Exit> {
+        let a = self.regs[ra];
+        let condition = match opcode {
+            80 => {
+                self.regs[ra] = vx;
+                true
+            }
+            81 => a == vx,
+            82 => a != vx,
+            83 => a < vx,
+            84 => a <= vx,
+            85 => a >= vx,
+            86 => a > vx,
+            87 => (a as i64) < (vx as i64),
+            88 => (a as i64) <= (vx as i64),
+            89 => (a as i64) >= (vx as i64),
+            _ => (a as i64) > (vx as i64),
+        };
+
+        self.branch(target, condition)
+    }
+
+    /// Instructions with two registers
+    fn two_regs(&mut self, opcode: u8, rd: usize, ra: usize) -> Result<(), Exit> {
+        let a = self.regs[ra];
+        self.regs[rd] = match opcode {
+            100 => a,
+            101 => self.memory.sbrk(a).map(u64::from).unwrap_or(0),
+            102 => a.count_ones() as u64,
+            103 => (a as u32).count_ones() as u64,
+            104 => a.leading_zeros() as u64,
+            105 => (a as u32).leading_zeros() as u64,
+            106 => a.trailing_zeros() as u64,
+            107 => (a as u32).trailing_zeros() as u64,
+            108 => a as u8 as i8 as i64 as u64,
+            109 => a as u16 as i16 as i64 as u64,
+            110 => a as u16 as u64,
+            _ => a.swap_bytes(),
+        };
+
+        Ok(())
+    }
+
+    /// Instructions with two registers and one immediate
+    fn two_regs_one_imm(&mut self, opcode: u8, ra: usize, rb: usize, vx: u64) -> Result<(), Exit> {
+        let b = self.regs[rb];
+        let value = match opcode {
+            120..=123 => return self.store(b.wrapping_add(vx), self.regs[ra], width(opcode - 120)),
+            124..=130 => {
+                let (len, signed) = match opcode {
+                    124 => (1, false),
+                    125 => (1, true),
+                    126 => (2, false),
+                    127 => (2, true),
+                    128 => (4, false),
+                    129 => (4, true),
+                    _ => (8, false),
+                };
+                self.load(b.wrapping_add(vx), len, signed)?
+            }
+            131 => sext32(b.wrapping_add(vx)),
+            132 => b & vx,
+            133 => b ^ vx,
+            134 => b | vx,
+            135 => sext32(b.wrapping_mul(vx)),
+            136 => (b < vx) as u64,
+            137 => ((b as i64) < (vx as i64)) as u64,
+            138 => sext32(((b as u32) << (vx % 32)) as u64),
+            139 => sext32(((b as u32) >> (vx % 32)) as u64),
+            140 => ((b as u32 as i32) >> (vx % 32)) as i64 as u64,
+            141 => sext32(vx.wrapping_sub(b)),
+            142 => (b > vx) as u64,
+            143 => ((b as i64) > (vx as i64)) as u64,
+            144 => sext32(((vx as u32) << (b % 32)) as u64),
+            145 => sext32(((vx as u32) >> (b % 32)) as u64),
+            146 => ((vx as u32 as i32) >> (b % 32)) as i64 as u64,
+            147 if b == 0 => vx,
+            148 if b != 0 => vx,
+            147 | 148 => self.regs[ra],
+            149 => b.wrapping_add(vx),
+            150 => b.wrapping_mul(vx),
+            151 => b << (vx % 64),
+            152 => b >> (vx % 64),
+            153 => ((b as i64) >> (vx % 64)) as u64,
+            154 => vx.wrapping_sub(b),
+            155 => vx << (b % 64),
+            156 => vx >> (b % 64),
+            157 => ((vx as i64) >> (b % 64)) as u64,
+            158 => b.rotate_right((vx % 64) as u32),
+            159 => vx.rotate_right((b % 64) as u32),
+            160 => sext32((b as u32).rotate_right((vx % 32) as u32) as u64),
+            _ => sext32((vx as u32).rotate_right((b % 32) as u32) as u64),
+        };
+
+        self.regs[ra] = value;
+        Ok(())
+    }
+
+    /// Instructions with three registers
+    fn three_regs(&mut self, opcode: u8, ra: usize, rb: usize, rd: usize) {
+        let (a, b) = (self.regs[ra], self.regs[rb]);
+        let (a32, b32) = (a as u32, b as u32);
+        let (sa32, sb32) = (a32 as i32, b32 as i32);
+        let (sa, sb) = (a as i64, b as i64);
+        self.regs[rd] = match opcode {
+            190 => sext32(a.wrapping_add(b)),
+            191 => sext32(a.wrapping_sub(b)),
+            192 => sext32(a.wrapping_mul(b)),
+            193 if b32 == 0 => u64::MAX,
+            193 => sext32((a32 / b32) as u64),
+            194 if b32 == 0 => u64::MAX,
+            194 if sa32 == i32::MIN && sb32 == -1 => sa32 as i64 as u64,
+            194 => (sa32 / sb32) as i64 as u64,
+            195 if b32 == 0 => sext32(a32 as u64),
+            195 => sext32((a32 % b32) as u64),
+            196 if b32 == 0 => sa32 as i64 as u64,
+            196 if sa32 == i32::MIN && sb32 == -1 => 0,
+            196 => (sa32 % sb32) as i64 as u64,
+            197 => sext32((a32 << (b % 32)) as u64),
+            198 => sext32((a32 >> (b % 32)) as u64),
+            199 => (sa32 >> (b % 32)) as i64 as u64,
+            200 => a.wrapping_add(b),
+            201 => a.wrapping_sub(b),
+            202 => a.wrapping_mul(b),
+            203 if b == 0 => u64::MAX,
+            203 => a / b,
+            204 if b == 0 => u64::MAX,
+            204 if sa == i64::MIN && sb == -1 => a,
+            204 => (sa / sb) as u64,
+            205 if b == 0 => a,
+            205 => a % b,
+            206 if b == 0 => a,
+            206 if sa == i64::MIN && sb == -1 => 0,
+            206 => (sa % sb) as u64,
+            207 => a << (b % 64),
+            208 => a >> (b % 64),
+            209 => (sa >> (b % 64)) as u64,
+            210 => a & b,
+            211 => a ^ b,
+            212 => a | b,
+            213 => ((sa as i128 * sb as i128) >> 64) as u64,
+            214 => ((a as u128 * b as u128) >> 64) as u64,
+            215 => ((sa as i128 * b as i128) >> 64) as u64,
+            216 => (a < b) as u64,
+            217 => (sa < sb) as u64,
+            218 if b == 0 => a,
+            219 if b != 0 => a,
+            218 | 219 => self.regs[rd],
+            220 => a.rotate_left((b % 64) as u32),
+            221 => sext32(a32.rotate_left((b % 32) as u32) as u64),
+            222 => a.rotate_right((b % 64) as u32),
+            223 => sext32(a32.rotate_right((b % 32) as u32) as u64),
+            224 => a & !b,
+            225 => a | !b,
+            226 => !(a ^ b),
+            227 => sa.max(sb) as u64,
+            228 => a.max(b),
+            229 => sa.min(sb) as u64,
+            _ => a.min(b),
+        };
+    }
+
+    /// Load a value from the memory
+    fn load(&self, address: u64, len: usize, signed: bool) -> Result<u64, Exit> {
+        let value = self.memory.read_int(address & 0xffff_ffff, len)?;
+        if !signed {
+            return Ok(value);
+        }
+
+        let shift = 64 - 8 * len as u32;
+        Ok((((value << shift) as i64) >> shift) as u64)
+    }
+
+    /// Store a value to the memory
+    fn store(&mut self, address: u64, value: u64, len: usize) -> Result<(), Exit> {
+        self.memory
+            .write_int(address & 0xffff_ffff, value, len)
+            .map_err(Into::into)
+    }
+
+    /// Branch to the target if the condition holds
+    fn branch(&mut self, target: u32, condition: bool) -> Result<(), Exit> {
+        if !condition {
+            return Ok(());
+        }
+
+        if !self.program.blocks.contains(&target) {
+            return Err(Exit::Panic);
+        }
+
+        self.pc = target;
+        Ok(())
+    }
+
+    /// Jump to the address in the jump table
+    fn djump(&mut self, address: u64) -> Result<(), Exit> {
+        let address = address & 0xffff_ffff;
+        if address == HALT_ADDRESS {
+            return Err(Exit::Halt);
+        }
+
+        self.pc = self.program.djump(address as u32).ok_or(Exit::Panic)?;
+        Ok(())
+    }
+}
+
+/// Decode a register index
+fn reg(byte: u8) -> usize {
+    ((byte & 0x0f) as usize).min(REGISTERS - 1)
+}
+
+/// Decode a sign-extended immediate
+fn imm(bytes: &[u8], len: usize) -> u64 {
+    if len == 0 {
+        return 0;
+    }
+
+    let mut value = [0; 8];
+    value[..len].copy_from_slice(&bytes[..len]);
+    let shift = 64 - 8 * len as u32;
+    (((u64::from_le_bytes(value) << shift) as i64) >> shift) as u64
+}
+
+/// Compute the target of a relative jump
+fn offset(pc: u32, imm: u64) -> u32 {
+    (pc as u64).wrapping_add(imm) as u32
+}
+
+/// Get the width of a store instruction
+fn width(index: u8) -> usize {
+    1 << index
+}
+
+/// Sign-extend the lower 32 bits
+fn sext32(value: u64) -> u64 {
+    value as u32 as i32 as i64 as u64
+}
@@ -0,0 +1,494 @@
+//! Execution tracing of the invocations
+//!
+//! Tracing is configured per thread, the steps are recorded into a bounded
+//! ring buffer and streamed to the step hook if any. Steps are only
+//! recorded by the pure backend, tracing returns [`Error::Unsupported`] on
+//! the native one.
+
+use crate::Error;
+use core::fmt;
+use std::{cell::RefCell, collections::VecDeque};
+
+thread_local! {
+    /// The tracer of the current thread
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+/// A host call made by the guest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostCall {
+    /// The index of the host call
+    pub id: u32,
+
+    /// The arguments in the registers 7 to 12
+    pub args: [u64; 6],
+
+    /// The result in the register 7
+    pub result: u64,
+}
+
+/// An executed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The program counter of the instruction
+    pub pc: u32,
+
+    /// The opcode of the instruction
+    pub opcode: u8,
+
+    /// The remaining gas after the step
+    pub gas: i64,
+
+    /// The changed registers with their new values
+    pub regs: Vec<(u8, u64)>,
+
+    /// The host call made by the instruction
+    pub host: Option<HostCall>,
+}
+
+impl Step {
+    /// Create a step from the registers before and after it
+    pub fn new(
+        pc: u32,
+        opcode: u8,
+        gas: i64,
+        before: &[u64],
+        after: &[u64],
+        host: Option<HostCall>,
+    ) -> Self {
+        let regs = before
+            .iter()
+            .zip(after)
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(index, (_, new))| (index as u8, *new))
+            .collect();
+
+        Self {
+            pc,
+            opcode,
+            gas,
+            regs,
+            host,
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} {:<20} gas={}",
+            self.pc,
+            self::mnemonic(self.opcode),
+            self.gas
+        )?;
+        for (reg, value) in &self.regs {
+            write!(f, " r{reg}={value:#x}")?;
+        }
+
+        if let Some(host) = &self.host {
+            write!(
+                f,
+                " host={}({:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x}) -> {:#x}",
+                host.id,
+                host.args[0],
+                host.args[1],
+                host.args[2],
+                host.args[3],
+                host.args[4],
+                host.args[5],
+                host.result
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hook called on every recorded step
+type Hook = Box<dyn FnMut(&Step)>;
+
+/// Tracer of the current thread
+struct Tracer {
+    /// The capacity of the ring buffer
+    capacity: usize,
+
+    /// The recorded steps
+    steps: VecDeque<Step>,
+
+    /// The step hook
+    hook: Option<Hook>,
+
+    /// The tracer replaced by a capture
+    outer: Option<Box<Tracer>>,
+}
+
+impl Tracer {
+    /// Create a tracer keeping the last `capacity` steps
+    fn new(capacity: usize, hook: Option<Hook>) -> Self {
+        Self {
+            capacity,
+            steps: VecDeque::with_capacity(capacity.min(1 << 16)),
+            hook,
+            outer: None,
+        }
+    }
+
+    /// Push a step into the ring buffers
+    fn push(&mut self, step: Step) {
+        if let Some(outer) = self.outer.as_mut() {
+            outer.push(step.clone());
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step);
+    }
+}
+
+/// If the steps are recorded by the selected backend
+pub const fn supported() -> bool {
+    cfg!(feature = "pure")
+}
+
+/// Start tracing the last `capacity` steps on the current thread
+pub fn start(capacity: usize) -> Result<(), Error> {
+    self::check()?;
+    TRACER.with_borrow_mut(|tracer| {
+        let hook = tracer.take().and_then(|tracer| tracer.hook);
+        *tracer = Some(Tracer::new(capacity, hook));
+    });
+    Ok(())
+}
+
+/// Stream every step to the hook on the current thread
+///
+/// Starts tracing without buffering if it is not started yet.
+pub fn on_step(hook: impl FnMut(&Step) + 'static) -> Result<(), Error> {
+    self::check()?;
+    TRACER.with_borrow_mut(|tracer| {
+        tracer.get_or_insert_with(|| Tracer::new(0, None)).hook = Some(Box::new(hook));
+    });
+    Ok(())
+}
+
+/// Trace the last `capacity` steps of a call
+///
+/// The previous tracing state of the thread is restored after the call, the
+/// step hook keeps receiving the steps, and the steps are recorded into the
+/// previous ring buffer as well.
+pub fn capture<T>(capacity: usize, call: impl FnOnce() -> T) -> Result<(T, Vec<Step>), Error> {
+    self::check()?;
+    TRACER.with_borrow_mut(|tracer| {
+        let mut previous = tracer.take();
+        let hook = previous.as_mut().and_then(|tracer| tracer.hook.take());
+        let mut current = Tracer::new(capacity, hook);
+        current.outer = previous.map(Box::new);
+        *tracer = Some(current);
+    });
+
+    let result = call();
+    let current = TRACER
+        .with_borrow_mut(Option::take)
+        .expect("installed above");
+    let previous = current.outer.map(|mut previous| {
+        previous.hook = current.hook;
+        *previous
+    });
+
+    TRACER.with_borrow_mut(|tracer| *tracer = previous);
+    Ok((result, current.steps.into()))
+}
+
+/// Take the recorded steps, tracing continues
+pub fn take() -> Vec<Step> {
+    TRACER.with_borrow_mut(|tracer| {
+        tracer
+            .as_mut()
+            .map(|tracer| tracer.steps.drain(..).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Stop tracing on the current thread, returns the recorded steps
+pub fn stop() -> Vec<Step> {
+    TRACER
+        .with_borrow_mut(Option::take)
+        .map(|tracer| tracer.steps.into())
+        .unwrap_or_default()
+}
+
+/// If tracing is enabled on the current thread
+pub fn enabled() -> bool {
+    TRACER.with_borrow(Option::is_some)
+}
+
+/// Record a step
+pub fn record(step: Step) {
+    TRACER.with_borrow_mut(|tracer| {
+        let Some(tracer) = tracer else {
+            return;
+        };
+
+        if let Some(hook) = tracer.hook.as_mut() {
+            hook(&step);
+        }
+
+        tracer.push(step);
+    });
+}
+
+/// Check that the selected backend records the steps
+fn check() -> Result<(), Error> {
+    if self::supported() {
+        Ok(())
+    } else {
+        Err(Error::Unsupported {
+            call: "trace",
+            feature: "step tracing",
+        })
+    }
+}
+
+/// Get the mnemonic of an opcode
+pub fn mnemonic(opcode: u8) -> &'static str {
+    match opcode {
+        0 => "trap",
+        1 => "fallthrough",
+        10 => "ecalli",
+        20 => "load_imm_64",
+        30 => "store_imm_u8",
+        31 => "store_imm_u16",
+        32 => "store_imm_u32",
+        33 => "store_imm_u64",
+        40 => "jump",
+        50 => "jump_ind",
+        51 => "load_imm",
+        52 => "load_u8",
+        53 => "load_i8",
+        54 => "load_u16",
+        55 => "load_i16",
+        56 => "load_u32",
+        57 => "load_i32",
+        58 => "load_u64",
+        59 => "store_u8",
+        60 => "store_u16",
+        61 => "store_u32",
+        62 => "store_u64",
+        70 => "store_imm_ind_u8",
+        71 => "store_imm_ind_u16",
+        72 => "store_imm_ind_u32",
+        73 => "store_imm_ind_u64",
+        80 => "load_imm_jump",
+        81 => "branch_eq_imm",
+        82 => "branch_ne_imm",
+        83 => "branch_lt_u_imm",
+        84 => "branch_le_u_imm",
+        85 => "branch_ge_u_imm",
+        86 => "branch_gt_u_imm",
+        87 => "branch_lt_s_imm",
+        88 => "branch_le_s_imm",
+        89 => "branch_ge_s_imm",
+        90 => "branch_gt_s_imm",
+        100 => "move_reg",
+        101 => "sbrk",
+        102 => "count_set_bits_64",
+        103 => "count_set_bits_32",
+        104 => "leading_zero_bits_64",
+        105 => "leading_zero_bits_32",
+        106 => "trailing_zero_bits_64",
+        107 => "trailing_zero_bits_32",
+        108 => "sign_extend_8",
+        109 => "sign_extend_16",
+        110 => "zero_extend_16",
+        111 => "reverse_bytes",
+        120 => "store_ind_u8",
+        121 => "store_ind_u16",
+        122 => "store_ind_u32",
+        123 => "store_ind_u64",
+        124 => "load_ind_u8",
+        125 => "load_ind_i8",
+        126 => "load_ind_u16",
+        127 => "load_ind_i16",
+        128 => "load_ind_u32",
+        129 => "load_ind_i32",
+        130 => "load_ind_u64",
+        131 => "add_imm_32",
+        132 => "and_imm",
+        133 => "xor_imm",
+        134 => "or_imm",
+        135 => "mul_imm_32",
+        136 => "set_lt_u_imm",
+        137 => "set_lt_s_imm",
+        138 => "shlo_l_imm_32",
+        139 => "shlo_r_imm_32",
+        140 => "shar_r_imm_32",
+        141 => "neg_add_imm_32",
+        142 => "set_gt_u_imm",
+        143 => "set_gt_s_imm",
+        144 => "shlo_l_imm_alt_32",
+        145 => "shlo_r_imm_alt_32",
+        146 => "shar_r_imm_alt_32",
+        147 => "cmov_iz_imm",
+        148 => "cmov_nz_imm",
+        149 => "add_imm_64",
+        150 => "mul_imm_64",
+        151 => "shlo_l_imm_64",
+        152 => "shlo_r_imm_64",
+        153 => "shar_r_imm_64",
+        154 => "neg_add_imm_64",
+        155 => "shlo_l_imm_alt_64",
+        156 => "shlo_r_imm_alt_64",
+        157 => "shar_r_imm_alt_64",
+        158 => "rot_r_64_imm",
+        159 => "rot_r_64_imm_alt",
+        160 => "rot_r_32_imm",
+        161 => "rot_r_32_imm_alt",
+        170 => "branch_eq",
+        171 => "branch_ne",
+        172 => "branch_lt_u",
+        173 => "branch_lt_s",
+        174 => "branch_ge_u",
+        175 => "branch_ge_s",
+        180 => "load_imm_jump_ind",
+        190 => "add_32",
+        191 => "sub_32",
+        192 => "mul_32",
+        193 => "div_u_32",
+        194 => "div_s_32",
+        195 => "rem_u_32",
+        196 => "rem_s_32",
+        197 => "shlo_l_32",
+        198 => "shlo_r_32",
+        199 => "shar_r_32",
+        200 => "add_64",
+        201 => "sub_64",
+        202 => "mul_64",
+        203 => "div_u_64",
+        204 => "div_s_64",
+        205 => "rem_u_64",
+        206 => "rem_s_64",
+        207 => "shlo_l_64",
+        208 => "shlo_r_64",
+        209 => "shar_r_64",
+        210 => "and",
+        211 => "xor",
+        212 => "or",
+        213 => "mul_upper_s_s",
+        214 => "mul_upper_u_u",
+        215 => "mul_upper_s_u",
+        216 => "set_lt_u",
+        217 => "set_lt_s",
+        218 => "cmov_iz",
+        219 => "cmov_nz",
+        220 => "rot_l_64",
+        221 => "rot_l_32",
+        222 => "rot_r_64",
+        223 => "rot_r_32",
+        224 => "and_inv",
+        225 => "or_inv",
+        226 => "xnor",
+        227 => "max",
+        228 => "max_u",
+        229 => "min",
+        230 => "min_u",
+        _ => "invalid",
+    }
+}
+
+/// Get the name of a host call
+pub fn host_name(id: u32) -> &'static str {
+    match id {
+        0 => "gas",
+        1 => "fetch",
+        2 => "lookup",
+        3 => "read",
+        4 => "write",
+        5 => "info",
+        6 => "historical_lookup",
+        7 => "export",
+        8 => "machine",
+        9 => "peek",
+        10 => "poke",
+        11 => "pages",
+        12 => "invoke",
+        13 => "expunge",
+        14 => "bless",
+        15 => "assign",
+        16 => "designate",
+        17 => "checkpoint",
+        18 => "new",
+        19 => "upgrade",
+        20 => "transfer",
+        21 => "eject",
+        22 => "query",
+        23 => "solicit",
+        24 => "forget",
+        25 => "yield",
+        26 => "provide",
+        100 => "log",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A step at the given program counter
+    #[cfg(feature = "pure")]
+    fn step(pc: u32) -> Step {
+        Step::new(pc, 0, 0, &[], &[], None)
+    }
+
+    #[cfg(feature = "pure")]
+    #[test]
+    fn capture_restores_the_previous_state() {
+        let seen = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let hook = seen.clone();
+        self::start(4).unwrap();
+        self::on_step(move |step| hook.borrow_mut().push(step.pc)).unwrap();
+        self::record(self::step(1));
+
+        let ((), steps) =
+            self::capture(2, || (2..5).for_each(|pc| self::record(self::step(pc)))).unwrap();
+        assert_eq!(steps, vec![self::step(3), self::step(4)]);
+
+        self::record(self::step(5));
+        assert_eq!(*seen.borrow(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            self::stop(),
+            (2..6).map(self::step).collect::<Vec<_>>(),
+            "the captured steps are kept in the previous ring buffer"
+        );
+    }
+
+    #[cfg(feature = "pure")]
+    #[test]
+    fn capture_without_previous_state() {
+        let ((), steps) = self::capture(8, || self::record(self::step(1))).unwrap();
+        assert_eq!(steps, vec![self::step(1)]);
+        assert!(!self::enabled());
+    }
+
+    #[cfg(not(feature = "pure"))]
+    #[test]
+    fn unsupported_on_native() {
+        assert!(matches!(self::start(8), Err(Error::Unsupported { .. })));
+        assert!(matches!(
+            self::on_step(|_| {}),
+            Err(Error::Unsupported { .. })
+        ));
+        assert!(matches!(
+            self::capture(8, || ()),
+            Err(Error::Unsupported { .. })
+        ));
+        assert!(!self::enabled());
+    }
+}
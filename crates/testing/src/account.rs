@@ -1,6 +1,7 @@
 //! Service account builder
 
-use crate::{Jam, key};
+use crate::Jam;
+use anyhow::{Result, anyhow};
 use service::{OpaqueHash, ServiceId, service::ServiceAccount};
 
 impl Jam {
@@ -30,17 +31,31 @@ impl Jam {
         hash
     }
 
-    /// Get a storage of an account
-    pub fn get_storage<V: serde::de::DeserializeOwned>(
-        &self,
+    /// Expire a preimage of the service account `slots` after the best block
+    ///
+    /// Closes the availability window of the preimage as an unrequest does,
+    /// the preimage is kept until the service forgets it.
+    pub fn expire_preimage(
+        &mut self,
         service: ServiceId,
-        key: &[u8],
-    ) -> Option<V> {
-        let account = self.chain.accounts.get(&service)?;
-        let vkey = key.to_vec();
-        let key = key::storage(service, &codec::encode(&vkey).ok()?);
-        let encoded = account.storage.get(key.as_ref())?;
-        codec::decode(&encoded[..]).ok()
+        hash: OpaqueHash,
+        slots: u32,
+    ) -> Result<()> {
+        let slot = self.chain.best.slot.saturating_add(slots);
+        let history = self
+            .chain
+            .accounts
+            .get_mut(&service)
+            .and_then(|account| account.lookup.iter_mut().find(|((key, _), _)| *key == hash))
+            .map(|(_, history)| history)
+            .ok_or_else(|| anyhow!("preimage 0x{} not found", hex::encode(hash)))?;
+
+        *history = match history.as_slice() {
+            [x] => vec![*x, slot],
+            [_, _, z] => vec![*z, slot],
+            _ => anyhow::bail!("preimage 0x{} is not available", hex::encode(hash)),
+        };
+        Ok(())
     }
 
     /// Set the code of the service account
@@ -1,10 +1,14 @@
 //! Authorization related stuffs
 
 use crate::Jam;
-use service::{OpaqueHash, ServiceId, service::ServiceAccount};
+use anyhow::Result;
+use service::{
+    CORES_COUNT, OpaqueHash, ServiceId,
+    service::{ServiceAccount, WorkPackage},
+};
 
 /// Authorization related stuffs
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Auth {
     /// The authorization token
     pub token: Vec<u8>,
@@ -38,10 +42,17 @@ impl Auth {
         self.config = config;
         self
     }
+
+    /// The authorizer hash of the code hash and the config
+    pub fn hash(&self) -> OpaqueHash {
+        self::authorizer_hash(&self.code_hash, &self.config)
+    }
 }
 
 impl Jam {
     /// Set the authorization
+    ///
+    /// The authorizer fills the pools and the queues of every core.
     pub fn with_auth(mut self, service: ServiceId, code: Vec<u8>) -> Self {
         let mut auth = ServiceAccount::default();
         auth.info.balance = 1000;
@@ -58,6 +69,7 @@ impl Jam {
 
         self.auth.code_hash = hash;
         self.auth.host = service;
+        self.authorize_cores();
         self
     }
 
@@ -68,14 +80,75 @@ impl Jam {
     }
 
     /// Set the authorizer config
+    ///
+    /// The authorizer fills the pools and the queues of every core.
     pub fn with_auth_config(mut self, config: Vec<u8>) -> Self {
         self.auth.config = config;
+        self.authorize_cores();
         self
     }
 
     /// Set the authorization
+    ///
+    /// The authorizer fills the pools and the queues of every core.
     pub fn with_authorizer(mut self, auth: Auth) -> Self {
         self.auth = auth;
+        self.authorize_cores();
+        self
+    }
+
+    /// Set the authorizer pool of a core, below [`CORES_COUNT`]
+    pub fn with_auth_pool(mut self, core: u16, pool: Vec<OpaqueHash>) -> Result<Self> {
+        self.chain.pools[self::core(core)?] = pool;
+        Ok(self)
+    }
+
+    /// Set the authorizer queue of a core, which rotates into the pool
+    pub fn with_auth_queue(mut self, core: u16, queue: Vec<OpaqueHash>) -> Result<Self> {
+        self.chain.queues[self::core(core)?] = queue;
+        Ok(self)
+    }
+
+    /// Set the service which assigns the authorizer queue of a core
+    pub fn with_assigner(mut self, core: u16, service: ServiceId) -> Result<Self> {
+        self.chain.privileges.assign[self::core(core)?] = service;
+        Ok(self)
+    }
+
+    /// Let the cores with an empty pool accept any authorizer
+    pub fn with_open_pools(mut self) -> Self {
+        self.chain.open_pools = true;
         self
     }
+
+    /// Fill the pools and the queues of every core with the authorizer
+    fn authorize_cores(&mut self) {
+        let hash = self.auth.hash();
+        let queue = vec![hash; self.chain.config.constants.auth_queue as usize];
+        for (pool, authorization) in self.chain.pools.iter_mut().zip(&mut self.chain.queues) {
+            *pool = vec![hash];
+            *authorization = queue.clone();
+        }
+    }
+}
+
+/// The index of a core, below [`CORES_COUNT`]
+pub(crate) fn core(core: u16) -> Result<usize> {
+    if core as usize >= CORES_COUNT {
+        anyhow::bail!("core {core} out of {CORES_COUNT} cores");
+    }
+
+    Ok(core as usize)
+}
+
+/// The authorizer hash of a work package
+pub(crate) fn package_authorizer(work: &WorkPackage) -> OpaqueHash {
+    self::authorizer_hash(&work.auth_code_hash, &work.config)
+}
+
+/// Hash the authorizer code hash with its config
+fn authorizer_hash(code_hash: &OpaqueHash, config: &[u8]) -> OpaqueHash {
+    let mut authorizer = code_hash.to_vec();
+    authorizer.extend_from_slice(config);
+    service::blake2b(&authorizer)
 }
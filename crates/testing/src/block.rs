@@ -0,0 +1,140 @@
+//! Block simulation with work packages on several cores
+
+use crate::{ExecutionInfo, Jam, auth};
+use anyhow::Result;
+use service::service::{WorkExecResult, WorkPackage};
+use std::collections::BTreeMap;
+
+impl Jam {
+    /// Submit a work package to a core for the next block
+    ///
+    /// A core takes one work package per block, within the refine and
+    /// accumulate gas limits of a package.
+    pub fn submit(&mut self, core: u16, package: WorkPackage) -> Result<()> {
+        let constants = self.chain.config.constants;
+        auth::core(core)?;
+        if self.submitted.contains_key(&core) {
+            anyhow::bail!("core {core} already has a work package");
+        }
+
+        if package.items.len() > constants.max_items as usize {
+            anyhow::bail!(
+                "{} work items exceed the limit of {}",
+                package.items.len(),
+                constants.max_items
+            );
+        }
+
+        let refine_gas = package
+            .items
+            .iter()
+            .map(|item| item.refine_gas_limit)
+            .sum::<u64>();
+        if refine_gas > constants.refine_gas {
+            anyhow::bail!(
+                "refine gas {refine_gas} exceeds the limit of {}",
+                constants.refine_gas
+            );
+        }
+
+        let accumulate_gas = package
+            .items
+            .iter()
+            .map(|item| item.accumulate_gas_limit)
+            .sum::<u64>();
+        if accumulate_gas > constants.accumulate_gas {
+            anyhow::bail!(
+                "accumulate gas {accumulate_gas} exceeds the limit of {}",
+                constants.accumulate_gas
+            );
+        }
+
+        self.submitted.insert(core, package);
+        Ok(())
+    }
+
+    /// Get the work packages submitted for the next block by core
+    pub fn submitted(&self) -> &BTreeMap<u16, WorkPackage> {
+        &self.submitted
+    }
+
+    /// Execute the submitted work packages in one block
+    ///
+    /// Every package is checked against the authorizer pool of its core,
+    /// authorized on the core and refined against the state before the block,
+    /// so the packages don't see the exports of each other. The packages are
+    /// refined one after the other on the calling thread, which yields the
+    /// same reports as refining them in parallel. The packages which are not
+    /// authorized are dropped, the reports of the others are then accumulated
+    /// together in order of the cores. The authorizers of the accumulated
+    /// packages leave the pools of their cores, which then take the
+    /// authorizer of the best slot from their queues.
+    ///
+    /// The submitted packages are only cleared once the block succeeds, they
+    /// are all kept if it fails.
+    pub fn execute_block(&mut self) -> Result<ExecutionInfo> {
+        if self.submitted.is_empty() {
+            anyhow::bail!("no work packages submitted");
+        }
+
+        let mut reports = Vec::new();
+        let mut exports = Vec::new();
+        let mut used = Vec::new();
+        for (core, package) in self.submitted.clone() {
+            let authorizer = auth::package_authorizer(&package);
+            if !self.chain.authorized(core, &authorizer) {
+                tracing::warn!(
+                    "authorizer 0x{} is not in the pool of core {core}, dropping the package",
+                    hex::encode(authorizer)
+                );
+                continue;
+            }
+
+            let authorized = self.authorize(&package, core)?;
+            let WorkExecResult::Ok(auth_output) = authorized.exec else {
+                tracing::warn!(
+                    "work package on core {core} is not authorized: {:?}, dropping the package",
+                    authorized.exec
+                );
+                continue;
+            };
+
+            let (report, segments) = self.refine_on(&package, core, auth_output)?;
+            reports.push(report);
+            exports.push(segments);
+            used.push((core, authorizer));
+        }
+
+        if reports.is_empty() {
+            anyhow::bail!("no work packages authorized");
+        }
+
+        for (report, segments) in reports.iter().zip(exports) {
+            self.segments.insert(report.package, segments);
+        }
+
+        let refine_gas = reports
+            .iter()
+            .flat_map(|report| report.digests.iter())
+            .map(|digest| digest.refine_load.gas_used)
+            .sum();
+        let results = reports.iter().flat_map(|report| report.results()).collect();
+
+        let pre = self.chain.accounts.clone();
+        let accumulated = self.accumulate(reports.clone())?;
+        self.submitted.clear();
+        for (core, authorizer) in used {
+            self.chain.consume(core, &authorizer);
+        }
+
+        self.chain.rotate(self.chain.best.slot);
+        let mut info = ExecutionInfo::new(&pre, accumulated, self.chain.accounts.clone());
+        info.refine_gas = refine_gas;
+        info.results = results;
+        info.reports = reports;
+        info.keys = self.keys.clone();
+        info.profile = self.take_profile();
+        info.logs = std::mem::take(&mut self.logs);
+        Ok(info)
+    }
+}
@@ -3,7 +3,7 @@
 use crate::Jam;
 use anyhow::Result;
 use service::{
-    ServiceId,
+    OpaqueHash, ServiceId,
     service::{WorkItem, WorkPackage},
 };
 
@@ -15,6 +15,13 @@ impl Jam {
         self.items.push(item);
     }
 
+    /// Add a prerequisite of the next work package
+    ///
+    /// The package is not accumulated until its prerequisites are.
+    pub fn add_prerequisite(&mut self, package: OpaqueHash) {
+        self.prerequisites.push(package);
+    }
+
     /// Build a work package
     pub fn build(&mut self) -> Result<WorkPackage> {
         let package = WorkPackage {
@@ -22,7 +29,9 @@ impl Jam {
             auth_code_host: self.auth.host,
             auth_code_hash: self.auth.code_hash,
             config: self.auth.config.clone(),
-            context: self.chain.refine_context(),
+            context: self
+                .chain
+                .refine_context(self.prerequisites.drain(..).collect()),
             items: self.items.drain(..).collect(),
         };
 
@@ -31,18 +40,29 @@ impl Jam {
 
     /// pack a work item
     pub fn pack(&mut self, service: ServiceId, payload: Vec<u8>) -> Result<()> {
-        let item = WorkItem {
-            service,
-            code_hash: self.chain.service(service)?,
-            payload,
-            refine_gas_limit: 1_000_000,
-            accumulate_gas_limit: 1_000_000,
-            import_segments: Default::default(),
-            extrinsic: Default::default(),
-            export_count: Default::default(),
-        };
+        let item = self.item(service, payload)?;
+        self.pack_item(item);
+        Ok(())
+    }
+
+    /// pack a work item with extrinsics
+    ///
+    /// The extrinsics are available to refine through the fetch host call,
+    /// the native backend fails to refine the package with
+    /// [`spacevm::Error::Unsupported`].
+    pub fn pack_with_extrinsics(
+        &mut self,
+        service: ServiceId,
+        payload: Vec<u8>,
+        extrinsics: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let item = extrinsics
+            .into_iter()
+            .fold(self.item(service, payload)?, |item, extrinsic| {
+                item.with_extrinsic(extrinsic)
+            });
 
-        self.add_item(item);
+        self.pack_item(item);
         Ok(())
     }
 
@@ -1,25 +1,42 @@
 //! Chain environment
 
+use crate::{ChainConfig, Jam, Report, da};
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
 use service::{
-    EntropyBuffer, OpaqueHash, ServiceId,
-    service::{RefineContext, ServiceAccount},
+    CORES_COUNT, EntropyBuffer, OpaqueHash, ServiceId,
+    service::{Privileges, RefineContext, ServiceAccount},
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// The number of blocks the finalized block trails the best block
+const FINALITY_DEPTH: usize = 2;
 
 /// Head of a block
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Head {
     /// Hash of the block
     pub hash: OpaqueHash,
 
     /// Slot of the block
     pub slot: u32,
+
+    /// State root of the block
+    pub state_root: OpaqueHash,
+
+    /// Beefy root of the block
+    pub beefy_root: OpaqueHash,
+
+    /// Accumulated entropy after the block
+    pub entropy: OpaqueHash,
 }
 
 /// Chain environment
 #[derive(Clone, Default)]
 pub struct Chain {
+    /// Chain parameters
+    pub config: ChainConfig,
+
     /// Best block
     pub best: Head,
 
@@ -31,6 +48,36 @@ pub struct Chain {
 
     /// Service accounts
     pub accounts: BTreeMap<u32, ServiceAccount>,
+
+    /// Seed of the entropy
+    pub seed: u64,
+
+    /// Privileged services
+    pub privileges: Privileges,
+
+    /// Authorizer pools by core
+    pub pools: [Vec<OpaqueHash>; CORES_COUNT],
+
+    /// Authorizer queues by core
+    pub queues: [Vec<OpaqueHash>; CORES_COUNT],
+
+    /// If the cores with an empty pool accept any authorizer
+    pub open_pools: bool,
+
+    /// The accumulated work packages
+    pub accumulated: BTreeSet<OpaqueHash>,
+
+    /// Recorded entropy of the next blocks
+    pub(crate) replay: VecDeque<OpaqueHash>,
+
+    /// Reports waiting for their prerequisites to be accumulated
+    pub(crate) ready: Vec<Report>,
+
+    /// Reports deferred to the next accumulation for the gas budget
+    pub(crate) deferred: Vec<Report>,
+
+    /// Recent blocks, from the oldest block to the best block
+    pub(crate) recent: VecDeque<Head>,
 }
 
 impl Chain {
@@ -42,17 +89,239 @@ impl Chain {
             .ok_or_else(|| anyhow!("Service not found"))
     }
 
-    /// Get the refine context
+    /// Produce a block at `slot`
+    ///
+    /// Accumulates the entropy of the block, or takes the next replayed
+    /// entropy if any, rotates the entropy buffer on every epoch boundary
+    /// since the parent and rotates the authorizer queues into the pools on
+    /// every slot since the parent. The availability windows of the preimages
+    /// close with the slot, the preimages are kept until they are forgotten.
+    pub fn produce(&mut self, slot: u32) -> Head {
+        let parent = self.best.clone();
+        let length = self.config.constants.epoch_length.max(1);
+        let epochs = (slot / length).saturating_sub(parent.slot / length);
+        for _ in 0..epochs.min(self.entropy.len() as u32) {
+            self.entropy = [
+                self.entropy[0],
+                self.entropy[0],
+                self.entropy[1],
+                self.entropy[2],
+            ];
+        }
+
+        self.entropy[0] = match self.replay.pop_front() {
+            Some(entropy) => entropy,
+            None => {
+                // the vrf output of the block is drawn from the seed
+                let mut vrf = self.seed.to_le_bytes().to_vec();
+                vrf.extend_from_slice(&slot.to_le_bytes());
+                let mut entropy = self.entropy[0].to_vec();
+                entropy.extend_from_slice(&service::blake2b(&vrf));
+                service::blake2b(&entropy)
+            }
+        };
+
+        let mut header = parent.hash.to_vec();
+        header.extend_from_slice(&slot.to_le_bytes());
+        header.extend_from_slice(&self.entropy[0]);
+        if self.recent.is_empty() {
+            self.recent.push_back(parent.clone());
+        }
+
+        // only the authorizers of the last `auth_pool` queue cycles can stay
+        // in the pools
+        let constants = self.config.constants;
+        let cycles = constants.auth_pool as u32 * constants.auth_queue.max(1) as u32;
+        for slot in (parent.slot + 1).max(slot.saturating_sub(cycles))..=slot {
+            self.rotate(slot);
+        }
+
+        // the beefy root stands in for the mmr of the recent blocks
+        self.best = Head {
+            hash: service::blake2b(&header),
+            slot,
+            state_root: service::blake2b(&codec::encode(&self.accounts).unwrap_or_default()),
+            beefy_root: da::merkle_root(self.recent.iter().map(|head| head.hash.as_slice())),
+            entropy: self.entropy[0],
+        };
+
+        // finalize the blocks behind the finality depth
+        self.recent.push_back(self.best.clone());
+        while self.recent.len() > self.config.constants.recent_history as usize {
+            self.recent.pop_front();
+        }
+
+        let finalized = self.recent.len().saturating_sub(FINALITY_DEPTH + 1);
+        self.finalized = self.recent[finalized].clone();
+        self.best.clone()
+    }
+
+    /// Rotate the authorizer of the slot from the queue into the pool
     ///
-    /// TODO: support prerequisites
-    pub fn refine_context(&self) -> RefineContext {
+    /// Cores with an empty queue keep their pool.
+    pub(crate) fn rotate(&mut self, slot: u32) {
+        let constants = self.config.constants;
+        let size = constants.auth_pool as usize;
+        for (queue, pool) in self.queues.iter().zip(self.pools.iter_mut()) {
+            let index = slot as usize % constants.auth_queue.max(1) as usize;
+            let Some(authorizer) = queue.get(index) else {
+                continue;
+            };
+
+            pool.push(*authorizer);
+            if pool.len() > size {
+                pool.drain(..pool.len() - size);
+            }
+        }
+    }
+
+    /// If the authorizer is in the pool of the core
+    ///
+    /// Cores with an empty pool only accept any authorizer with
+    /// [`Chain::open_pools`].
+    pub fn authorized(&self, core: u16, authorizer: &OpaqueHash) -> bool {
+        self.pools
+            .get(core as usize)
+            .is_some_and(|pool| pool.contains(authorizer) || (self.open_pools && pool.is_empty()))
+    }
+
+    /// Remove an authorizer used by a block from the pool of the core
+    pub(crate) fn consume(&mut self, core: u16, authorizer: &OpaqueHash) {
+        let Some(pool) = self.pools.get_mut(core as usize) else {
+            return;
+        };
+
+        if let Some(index) = pool.iter().position(|hash| hash == authorizer) {
+            pool.remove(index);
+        }
+    }
+
+    /// If a preimage of a service is available at the best block
+    pub fn available(&self, service: ServiceId, hash: &OpaqueHash) -> bool {
+        let slot = self.best.slot;
+        self.accounts.get(&service).is_some_and(|account| {
+            account.preimage.contains_key(hash)
+                && account
+                    .lookup
+                    .iter()
+                    .any(|((key, _), history)| key == hash && self::available(history, slot))
+        })
+    }
+
+    /// Get the refine context anchored to the best block
+    pub fn refine_context(&self, prerequisites: Vec<OpaqueHash>) -> RefineContext {
         RefineContext {
             anchor: self.best.hash,
-            state_root: Default::default(),
-            beefy_root: Default::default(),
+            state_root: self.best.state_root,
+            beefy_root: self.best.beefy_root,
             lookup_anchor: self.finalized.hash,
             lookup_anchor_slot: self.finalized.slot,
-            prerequisites: Default::default(),
+            prerequisites,
+        }
+    }
+
+    /// Get the recent blocks, from the oldest block to the best block
+    pub fn recent(&self) -> Vec<Head> {
+        if self.recent.is_empty() {
+            return vec![self.best.clone()];
         }
+
+        self.recent.iter().cloned().collect()
+    }
+
+    /// Validate the anchors of a refine context against the recent blocks
+    pub fn validate(&self, context: &RefineContext) -> Result<()> {
+        let anchor = self
+            .recent()
+            .into_iter()
+            .find(|head| head.hash == context.anchor)
+            .ok_or_else(|| {
+                anyhow!(
+                    "anchor 0x{} is not a recent block",
+                    hex::encode(context.anchor)
+                )
+            })?;
+
+        if anchor.state_root != context.state_root || anchor.beefy_root != context.beefy_root {
+            anyhow::bail!(
+                "roots of the anchor 0x{} mismatch",
+                hex::encode(context.anchor)
+            );
+        }
+
+        if context
+            .lookup_anchor_slot
+            .saturating_add(self.config.constants.max_lookup_age)
+            < self.best.slot
+        {
+            anyhow::bail!(
+                "lookup anchor at slot {} is too old",
+                context.lookup_anchor_slot
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Jam {
+    /// Get the chain environment
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Set the entropy buffer
+    pub fn with_entropy(mut self, entropy: EntropyBuffer) -> Self {
+        self.chain.entropy = entropy;
+        self
+    }
+
+    /// Seed the entropy, the entropy of every block is derived from the seed
+    ///
+    /// The entropy buffer is reset to entries derived from the seed as well.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.chain.seed = seed;
+        for (index, entropy) in self.chain.entropy.iter_mut().enumerate() {
+            let mut data = seed.to_le_bytes().to_vec();
+            data.push(index as u8);
+            *entropy = service::blake2b(&data);
+        }
+        self
+    }
+
+    /// Replay a recorded entropy sequence, e.g. the [`Head::entropy`] of the
+    /// blocks of another run, as the entropy of the next blocks
+    ///
+    /// The entropy is derived from the seed again once the sequence runs out.
+    pub fn replay_entropy(&mut self, entropy: Vec<OpaqueHash>) {
+        self.chain.replay = entropy.into();
+    }
+
+    /// Move time forward by `slots`, producing a block at the last slot
+    ///
+    /// The authorizer pools rotate on the skipped slots as well, and the
+    /// entropy buffer on the skipped epoch boundaries.
+    pub fn advance_slots(&mut self, slots: u32) -> Head {
+        if slots == 0 {
+            return self.chain.best.clone();
+        }
+
+        let slot = self.chain.best.slot.saturating_add(slots);
+        self.chain.produce(slot)
+    }
+
+    /// Produce a block at the next slot
+    pub fn produce_block(&mut self) -> Head {
+        self.advance_slots(1)
+    }
+}
+
+/// If a preimage is available at the given slot
+fn available(history: &[u32], slot: u32) -> bool {
+    match history {
+        [x] => *x <= slot,
+        [x, y] => *x <= slot && slot < *y,
+        [x, y, z] => (*x <= slot && slot < *y) || *z <= slot,
+        _ => false,
     }
 }
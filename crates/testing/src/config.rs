@@ -0,0 +1,145 @@
+//! Chain parameters of the testing environment
+
+use crate::Jam;
+use anyhow::Result;
+use service::{
+    CORES_COUNT,
+    api::{ValidatorData, ValidatorsData},
+};
+use spacevm::constants::Constants;
+
+/// Chain parameters
+///
+/// The protocol constants are also served to the services by the fetch host
+/// call of the `pure` backend. The core and validator counts are fixed by the
+/// service types, to [`CORES_COUNT`] cores and the length of
+/// [`ValidatorsData`].
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    /// Protocol constants
+    pub constants: Constants,
+
+    /// Keys of the validators
+    pub validators: ValidatorsData,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self::zeroed(self::scaled(Constants::preset()))
+    }
+}
+
+impl ChainConfig {
+    /// Create a config from the protocol constants with zeroed validator
+    /// keys
+    ///
+    /// The core and validator counts of the constants must match the service
+    /// types.
+    pub fn new(constants: Constants) -> Result<Self> {
+        let scaled = self::scaled(constants);
+        if constants.cores != scaled.cores {
+            anyhow::bail!(
+                "{} cores in the constants, expected {}",
+                constants.cores,
+                scaled.cores
+            );
+        }
+
+        if constants.validators != scaled.validators {
+            anyhow::bail!(
+                "{} validators in the constants, expected {}",
+                constants.validators,
+                scaled.validators
+            );
+        }
+
+        Ok(Self::zeroed(constants))
+    }
+
+    /// The parameters of the tiny chain
+    pub fn tiny() -> Self {
+        Self::zeroed(self::scaled(Constants::tiny()))
+    }
+
+    /// The parameters of the full chain, scaled down to the cores and
+    /// validators of the service types
+    pub fn full() -> Self {
+        Self::zeroed(self::scaled(Constants::full()))
+    }
+
+    /// Create a config from matching constants with zeroed validator keys
+    fn zeroed(constants: Constants) -> Self {
+        Self {
+            constants,
+            validators: self::keys(),
+        }
+    }
+
+    /// Set the validator keys
+    pub fn with_validators(mut self, validators: ValidatorsData) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Set the epoch length in slots
+    pub fn with_epoch_length(mut self, slots: u32) -> Self {
+        self.constants.epoch_length = slots;
+        self
+    }
+
+    /// Set the slot period in seconds
+    pub fn with_slot_period(mut self, seconds: u16) -> Self {
+        self.constants.slot_period = seconds;
+        self
+    }
+
+    /// Set the period in slots after which an unavailable preimage can be
+    /// forgotten
+    pub fn with_expunge_period(mut self, slots: u32) -> Self {
+        self.constants.expunge_period = slots;
+        self
+    }
+
+    /// Set the refine gas limit of a work package
+    pub fn with_refine_gas(mut self, gas: u64) -> Self {
+        self.constants.refine_gas = gas;
+        self
+    }
+
+    /// Set the accumulate gas limit of a work report
+    pub fn with_accumulate_gas(mut self, gas: u64) -> Self {
+        self.constants.accumulate_gas = gas;
+        self
+    }
+
+    /// Set the total accumulate gas of a block
+    pub fn with_total_accumulate_gas(mut self, gas: u64) -> Self {
+        self.constants.total_accumulate_gas = gas;
+        self
+    }
+}
+
+impl Jam {
+    /// Set the chain parameters
+    pub fn with_config(mut self, config: ChainConfig) -> Self {
+        self.chain.config = config;
+        self
+    }
+}
+
+/// Set the core and validator counts of the constants to the service types
+fn scaled(mut constants: Constants) -> Constants {
+    constants.cores = CORES_COUNT as u16;
+    constants.validators = self::keys().len() as u16;
+    constants
+}
+
+/// Zeroed validator keys
+fn keys() -> ValidatorsData {
+    std::array::from_fn(|_| ValidatorData {
+        bandersnatch: Default::default(),
+        ed25519: Default::default(),
+        bls: [0; 144],
+        metadata: [0; 128],
+    })
+}
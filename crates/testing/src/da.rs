@@ -0,0 +1,104 @@
+//! In-memory data availability store of the exported segments
+
+use anyhow::{Result, anyhow};
+use service::{
+    OpaqueHash, api,
+    service::{ImportSpec, result::Segment},
+};
+use std::collections::BTreeMap;
+
+/// Exported segments of the refined work packages
+///
+/// Segments are keyed by the segment root of their package. Imports refer to
+/// them by the segment root, or by the hash of the exporting package in place
+/// of the root, as the package-hash specifiers of the Graypaper.
+#[derive(Clone, Debug, Default)]
+pub struct SegmentStore {
+    /// Exported segments by the segment root
+    segments: BTreeMap<OpaqueHash, Vec<Segment>>,
+
+    /// Segment roots by the package hash
+    roots: BTreeMap<OpaqueHash, OpaqueHash>,
+}
+
+impl SegmentStore {
+    /// Store the exported segments of a package, returns the segment root
+    pub fn insert(&mut self, package: OpaqueHash, segments: Vec<Segment>) -> OpaqueHash {
+        let root = self::segment_root(&segments);
+        self.roots.insert(package, root);
+        self.segments.insert(root, segments);
+        root
+    }
+
+    /// Get the segment root of a package
+    pub fn root(&self, package: &OpaqueHash) -> Option<OpaqueHash> {
+        self.roots.get(package).copied()
+    }
+
+    /// Get an exported segment by the segment root
+    pub fn segment(&self, root: &OpaqueHash, index: u16) -> Option<&Segment> {
+        self.segments.get(root)?.get(index as usize)
+    }
+
+    /// Import a segment exported by a package by its segment root
+    pub fn import(&self, package: &OpaqueHash, index: u16) -> Result<ImportSpec> {
+        let tree_root = self
+            .root(package)
+            .ok_or_else(|| anyhow!("package 0x{} not refined", hex::encode(package)))?;
+        Ok(ImportSpec { tree_root, index })
+    }
+
+    /// Resolve the import specifiers of a work item
+    ///
+    /// The specifiers refer to a segment root, or to a package hash which is
+    /// looked up in the roots of the refined packages.
+    pub fn resolve(&self, imports: &[ImportSpec]) -> Result<Vec<Segment>> {
+        imports
+            .iter()
+            .map(|spec| {
+                let root = self.root(&spec.tree_root).unwrap_or(spec.tree_root);
+                self.segment(&root, spec.index).cloned().ok_or_else(|| {
+                    anyhow!(
+                        "segment 0x{}:{} not found",
+                        hex::encode(spec.tree_root),
+                        spec.index
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Convert the resolved imports of the work items to the refine arguments
+pub(crate) fn imports(segments: &[Vec<Segment>]) -> Vec<Vec<api::Segment>> {
+    segments
+        .iter()
+        .map(|item| item.iter().map(|segment| api::Segment(segment.0)).collect())
+        .collect()
+}
+
+/// Compute the constant-depth binary merkle root of the segments
+pub fn segment_root(segments: &[Segment]) -> OpaqueHash {
+    self::merkle_root(segments.iter().map(|segment| segment.0.as_slice()))
+}
+
+/// Compute the constant-depth binary merkle root of the leaves
+pub fn merkle_root<'a>(leaves: impl Iterator<Item = &'a [u8]>) -> OpaqueHash {
+    let mut nodes = leaves
+        .map(|leaf| self::node(b"leaf", leaf))
+        .collect::<Vec<_>>();
+    nodes.resize(nodes.len().next_power_of_two(), OpaqueHash::default());
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| self::node(b"node", &[pair[0], pair[1]].concat()))
+            .collect();
+    }
+
+    nodes.first().copied().unwrap_or_default()
+}
+
+/// Hash a merkle node with its prefix
+fn node(prefix: &[u8], data: &[u8]) -> OpaqueHash {
+    service::blake2b(&[prefix, data].concat())
+}
@@ -0,0 +1,261 @@
+//! State diff of the service accounts
+
+use crate::{Render, TrackedKeys, key};
+use service::{OpaqueHash, ServiceId, service::ServiceAccount};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write},
+};
+
+/// A change of a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<T> {
+    /// The value is added
+    Added(T),
+
+    /// The value is changed
+    Changed {
+        /// The value before
+        from: T,
+
+        /// The value after
+        to: T,
+    },
+
+    /// The value is removed
+    Removed(T),
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    /// Diff an optional value, returns none if it is unchanged
+    pub fn new(from: Option<&T>, to: Option<&T>) -> Option<Self> {
+        match (from, to) {
+            (None, Some(to)) => Some(Self::Added(to.clone())),
+            (Some(from), None) => Some(Self::Removed(from.clone())),
+            (Some(from), Some(to)) if from != to => Some(Self::Changed {
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Get the value after the change
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Self::Added(to) | Self::Changed { to, .. } => Some(to),
+            Self::Removed(_) => None,
+        }
+    }
+}
+
+/// The changes of a service account
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    /// The balance delta
+    pub balance: i128,
+
+    /// The storage changes by the storage key
+    pub storage: BTreeMap<Vec<u8>, Change<Vec<u8>>>,
+
+    /// The preimage changes by the preimage hash
+    pub preimages: BTreeMap<OpaqueHash, Change<Vec<u8>>>,
+
+    /// The lookup changes by the preimage hash and length
+    pub lookup: BTreeMap<(OpaqueHash, u32), Change<Vec<u32>>>,
+}
+
+impl AccountDiff {
+    /// Diff two versions of an account
+    pub fn new(pre: &ServiceAccount, post: &ServiceAccount) -> Self {
+        Self {
+            balance: post.info.balance as i128 - pre.info.balance as i128,
+            storage: self::diff(&pre.storage, &post.storage),
+            preimages: self::diff(&pre.preimage, &post.preimage),
+            lookup: self::diff(&pre.lookup, &post.lookup),
+        }
+    }
+
+    /// If nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.balance == 0
+            && self.storage.is_empty()
+            && self.preimages.is_empty()
+            && self.lookup.is_empty()
+    }
+}
+
+/// The changes of the service accounts against the pre-state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// The created services
+    pub created: BTreeSet<ServiceId>,
+
+    /// The removed services
+    pub removed: BTreeSet<ServiceId>,
+
+    /// The changes of the accounts, including the created and removed ones
+    pub accounts: BTreeMap<ServiceId, AccountDiff>,
+
+    /// The yielded accumulation outputs
+    pub outputs: BTreeMap<ServiceId, OpaqueHash>,
+}
+
+impl StateDiff {
+    /// Diff the service accounts
+    pub fn new(
+        pre: &BTreeMap<ServiceId, ServiceAccount>,
+        post: &BTreeMap<ServiceId, ServiceAccount>,
+    ) -> Self {
+        let mut diff = Self::default();
+        let empty = ServiceAccount::default();
+        for service in pre.keys().chain(post.keys()).collect::<BTreeSet<_>>() {
+            let (from, to) = (pre.get(service), post.get(service));
+            match (from, to) {
+                (None, Some(_)) => diff.created.insert(*service),
+                (Some(_), None) => diff.removed.insert(*service),
+                _ => false,
+            };
+
+            let account = AccountDiff::new(from.unwrap_or(&empty), to.unwrap_or(&empty));
+            if !account.is_empty() || from.is_none() || to.is_none() {
+                diff.accounts.insert(*service, account);
+            }
+        }
+
+        diff
+    }
+
+    /// If nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.outputs.is_empty()
+    }
+
+    /// Get the changes of an account
+    pub fn account(&self, service: ServiceId) -> Option<&AccountDiff> {
+        self.accounts.get(&service)
+    }
+
+    /// Get the change of a storage key of a service
+    pub fn storage(&self, service: ServiceId, key: &[u8]) -> Option<&Change<Vec<u8>>> {
+        let key = key::storage(service, key);
+        self.account(service)?.storage.get(key.as_ref())
+    }
+
+    /// Describe the changes, naming the storage keys found in `keys` and
+    /// rendering their values
+    pub fn describe(&self, keys: &TrackedKeys) -> String {
+        let mut out = String::new();
+        self.write(&mut out, keys).expect("write to string");
+        out
+    }
+
+    /// Write the description of the changes
+    fn write(&self, out: &mut impl Write, keys: &TrackedKeys) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(out, "no changes");
+        }
+
+        for (service, account) in self.accounts.iter() {
+            let status = if self.created.contains(service) {
+                " (created)"
+            } else if self.removed.contains(service) {
+                " (removed)"
+            } else {
+                ""
+            };
+            writeln!(out, "service {service}{status}:")?;
+            if account.balance != 0 {
+                writeln!(out, "  balance: {:+}", account.balance)?;
+            }
+
+            for (key, change) in account.storage.iter() {
+                let tracked = keys
+                    .iter()
+                    .find(|(original, _)| key::storage(*service, original).as_slice() == key);
+                let (name, render) = match tracked {
+                    Some((original, render)) => (self::readable(original), *render),
+                    None => (self::hex(key), self::readable as Render),
+                };
+                writeln!(
+                    out,
+                    "  storage {name}: {}",
+                    self::change(change, |value| render(value))
+                )?;
+            }
+
+            for (hash, change) in account.preimages.iter() {
+                let len = |preimage: &Vec<u8>| format!("{} bytes", preimage.len());
+                writeln!(
+                    out,
+                    "  preimage {}: {}",
+                    self::hex(hash),
+                    self::change(change, len)
+                )?;
+            }
+
+            for ((hash, len), change) in account.lookup.iter() {
+                let slots = |history: &Vec<u32>| format!("{history:?}");
+                writeln!(
+                    out,
+                    "  lookup {}:{len}: {}",
+                    self::hex(hash),
+                    self::change(change, slots)
+                )?;
+            }
+
+            if let Some(output) = self.outputs.get(service) {
+                writeln!(out, "  output: {}", self::hex(output))?;
+            }
+        }
+
+        for (service, output) in self.outputs.iter() {
+            if !self.accounts.contains_key(service) {
+                writeln!(out, "service {service}:\n  output: {}", self::hex(output))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &TrackedKeys::new())
+    }
+}
+
+/// Describe a change with the formatter of the values
+fn change<T>(change: &Change<T>, format: impl Fn(&T) -> String) -> String {
+    match change {
+        Change::Added(to) => format!("added {}", format(to)),
+        Change::Changed { from, to } => format!("{} -> {}", format(from), format(to)),
+        Change::Removed(from) => format!("removed {}", format(from)),
+    }
+}
+
+/// Render bytes as a string if they are printable, or as hex
+pub(crate) fn readable(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(name) if !name.is_empty() && !name.contains(char::is_control) => format!("{name:?}"),
+        _ => self::hex(bytes),
+    }
+}
+
+/// Encode bytes as `0x` prefixed hex
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Diff two maps
+fn diff<K: Ord + Clone, V: Clone + PartialEq>(
+    pre: &BTreeMap<K, V>,
+    post: &BTreeMap<K, V>,
+) -> BTreeMap<K, Change<V>> {
+    pre.keys()
+        .chain(post.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|key| Some((key.clone(), Change::new(pre.get(key), post.get(key))?)))
+        .collect()
+}
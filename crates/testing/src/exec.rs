@@ -1,20 +1,145 @@
 //! Execution API of JAM VM
 
-use crate::{Jam, key};
-use anyhow::Result;
+use crate::{Jam, StateDiff, TrackedKeys, auth, da, util};
+use anyhow::{Result, anyhow};
 use service::{
-    ServiceId,
-    api::{
-        AccumulateArgs, AccumulateState, Accumulated, AuthorizeArgs, Reason, RefineArgs,
-        ValidatorData,
-    },
+    OpaqueHash, ServiceId,
+    api::{AccumulateArgs, AccumulateState, Accumulated, AuthorizeArgs, Reason, RefineArgs},
     service::{
-        Privileges, RefineLoad, ServiceAccount, WorkDigest, WorkExecResult, WorkPackage,
-        result::Executed,
+        RefineLoad, ServiceAccount, WorkDigest, WorkExecResult, WorkPackage,
+        result::{Executed, Refined, Segment},
     },
-    vm::Operand,
+    vm::{DeferredTransfer, Operand},
+};
+use spacevm::{
+    Backend, constants,
+    logs::{self, Log},
+    profile::{self, Profile},
+    trace::{self, Step},
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The refine result of a work item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkResult {
+    /// Refine halted with the output
+    Ok(Vec<u8>),
+
+    /// Refine ran out of gas
+    OutOfGas,
+
+    /// Refine panicked
+    Panic,
+
+    /// Refine exported a different number of segments than declared
+    InvalidExports,
+
+    /// The outputs of the report exceed the maximum size
+    InvalidDigest,
+
+    /// The service code is not available
+    BadCode,
+
+    /// The service code exceeds the maximum size
+    CodeOversize,
+}
+
+impl WorkResult {
+    /// If refine succeeded
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok(_))
+    }
+}
+
+impl From<&WorkExecResult> for WorkResult {
+    fn from(result: &WorkExecResult) -> Self {
+        match result {
+            WorkExecResult::Ok(output) => Self::Ok(output.clone()),
+            WorkExecResult::OutOfGas => Self::OutOfGas,
+            WorkExecResult::Panic => Self::Panic,
+            WorkExecResult::InvalidExports => Self::InvalidExports,
+            WorkExecResult::InvalidDigest => Self::InvalidDigest,
+            WorkExecResult::BadCode => Self::BadCode,
+            WorkExecResult::CodeOversize => Self::CodeOversize,
+        }
+    }
+}
+
+/// The accumulation of a service
+#[derive(Debug, Default)]
+pub struct Accumulation {
+    /// The accumulate gas used
+    pub gas: u64,
+
+    /// The yielded accumulation output
+    pub output: Option<OpaqueHash>,
+
+    /// The exit reason of the last accumulation, the changes of a service
+    /// which did not halt are rolled back to its checkpoint
+    pub reason: Reason,
+
+    /// The deferred transfers sent by the service
+    pub transfers: Vec<DeferredTransfer>,
+}
+
+/// The report of a refined work package
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The hash of the work package
+    pub package: OpaqueHash,
+
+    /// The core the package is refined on
+    pub core: u16,
+
+    /// The segment root of the exports
+    pub exports_root: OpaqueHash,
+
+    /// The hash of the authorizer code and config
+    pub authorizer_hash: OpaqueHash,
+
+    /// The output of the authorizer
+    pub auth_output: Vec<u8>,
+
+    /// The digests of the work items
+    pub digests: Vec<WorkDigest>,
+
+    /// The packages which must be accumulated before this one
+    pub prerequisites: Vec<OpaqueHash>,
+}
+
+impl Report {
+    /// The accumulate gas of the digests
+    pub fn gas(&self) -> u64 {
+        self.digests
+            .iter()
+            .map(|digest| digest.accumulate_gas)
+            .sum()
+    }
+
+    /// The refine results of the work items
+    pub fn results(&self) -> Vec<WorkResult> {
+        self.digests
+            .iter()
+            .map(|digest| WorkResult::from(&digest.result))
+            .collect()
+    }
+
+    /// The operands of a service
+    fn operands(&self, service: ServiceId) -> impl Iterator<Item = Operand> + '_ {
+        self.digests
+            .iter()
+            .filter(move |digest| digest.service_id == service)
+            .map(|digest| Operand {
+                package: self.package,
+                exports_root: self.exports_root,
+                authorizer_hash: self.authorizer_hash,
+                auth_output: self.auth_output.clone(),
+                payload: digest.payload_hash,
+                gas: digest.accumulate_gas,
+                data: digest.result.clone(),
+            })
+    }
+}
 
 /// The result of an execution
 #[derive(Debug, Default)]
@@ -25,46 +150,88 @@ pub struct ExecutionInfo {
     /// The accumulate gas used
     pub accumulate_gas: u64,
 
-    /// The account changes
+    /// The refine results of the work items, in order of the cores
+    pub results: Vec<WorkResult>,
+
+    /// The reports of the refined work packages, in order of the cores
+    pub reports: Vec<Report>,
+
+    /// The accounts after the execution
     pub accounts: BTreeMap<ServiceId, ServiceAccount>,
+
+    /// The tracked original storage keys
+    pub keys: TrackedKeys,
+
+    /// The changes of the accounts against the pre-state
+    pub diff: StateDiff,
+
+    /// The accumulations of the services
+    pub services: BTreeMap<ServiceId, Accumulation>,
+
+    /// The gas profile, if profiling is enabled
+    pub profile: Profile,
+
+    /// The logs of the guests
+    pub logs: Vec<Log>,
 }
 
 impl ExecutionInfo {
     /// Create a new execution info
-    pub fn new(acc: Vec<Accumulated>) -> Self {
-        let mut info = Self::default();
-        for acc in acc.iter() {
+    ///
+    /// The accounts are merged across the accumulations into `post`, the
+    /// post-state of [`Jam::accumulate`].
+    pub fn new(
+        pre: &BTreeMap<ServiceId, ServiceAccount>,
+        acc: Vec<(ServiceId, Accumulated)>,
+        post: BTreeMap<ServiceId, ServiceAccount>,
+    ) -> Self {
+        let mut info = Self {
+            diff: StateDiff::new(pre, &post),
+            accounts: post,
+            ..Default::default()
+        };
+
+        for (service, acc) in acc.into_iter() {
             info.accumulate_gas += acc.gas;
 
-            // FIXME: need to merge account data
-            info.accounts = acc.context.accounts.clone();
+            let accumulation = info.services.entry(service).or_default();
+            accumulation.gas += acc.gas;
+            accumulation.output = acc.hash.or(accumulation.output);
+            accumulation.reason = acc.reason;
+            accumulation.transfers.extend(acc.transfers);
+            if let Some(hash) = acc.hash {
+                info.diff.outputs.insert(service, hash);
+            }
         }
 
         info
     }
 
-    /// Get a storage of an account
-    pub fn get_storage<V: serde::de::DeserializeOwned>(
-        &self,
-        service: ServiceId,
-        key: &[u8],
-    ) -> Option<V> {
-        let account = self.accounts.get(&service)?;
-        let vkey = key.to_vec();
-        let key = key::storage(service, &vkey);
-        let encoded = account.storage.get(key.as_ref())?;
-        codec::decode(encoded).ok()
+    /// Get the refine result of a work item
+    pub fn result(&self, item: usize) -> Option<&WorkResult> {
+        self.results.get(item)
+    }
+
+    /// Get the accumulation of a service
+    pub fn service(&self, service: ServiceId) -> Option<&Accumulation> {
+        self.services.get(&service)
+    }
+
+    /// Describe the state diff, naming the tracked storage keys
+    pub fn describe(&self) -> String {
+        self.diff.describe(&self.keys)
     }
 }
 
 impl Jam {
     /// Execute a work item directly
     ///
-    /// TODO: introduce better execution result
+    /// Submits the work package to the configured core and executes it in
+    /// a block, see [`Jam::execute_block`].
     pub fn execute(&mut self, service: ServiceId, payload: Vec<u8>) -> Result<ExecutionInfo> {
         let package = self.send(service, payload)?;
-        let result = self.refine(&package)?;
-        Ok(ExecutionInfo::new(self.accumulate(result)?))
+        self.submit(self.core, package)?;
+        self.execute_block()
     }
 
     /// Authorize the work package
@@ -76,120 +243,500 @@ impl Jam {
             hex::encode(work.auth_code_hash)
         );
 
-        spacevm::authorize(AuthorizeArgs {
-            package: work.clone(),
-            core_idx,
-            accounts: self.chain.accounts.clone(),
-            timeslot: self.chain.best.slot,
-        })
+        let (backend, args) = (
+            self.backend,
+            AuthorizeArgs {
+                package: work.clone(),
+                core_idx,
+                accounts: self.chain.accounts.clone(),
+                timeslot: self.chain.best.slot,
+            },
+        );
+        let (executed, steps) = self.traced(|| spacevm::authorize_with(backend, args))?;
+
+        if !executed.is_ok() {
+            tracing::error!(
+                "authorize failed: {:?}{}",
+                executed.exec,
+                self::dump(&steps)
+            );
+        }
+
+        Ok(executed)
     }
 
-    /// Refine the work package
+    /// Refine the work package with the output of its authorizer
+    ///
+    /// Runs refine for all work items on the configured core, the exports of
+    /// each item follow the exports of the previous items. The imports are
+    /// resolved from the segment store, and the exports of the package are
+    /// stored in it.
     ///
-    /// NOTE: run refine for all work items
+    /// Failed items are reported with their [`WorkResult`] in the digests.
+    pub fn refine(&mut self, work: &WorkPackage, auth_output: Vec<u8>) -> Result<Report> {
+        let (report, exports) = self.refine_on(work, self.core, auth_output)?;
+        self.segments.insert(report.package, exports);
+        Ok(report)
+    }
+
+    /// Refine the work package on a core, returns the report and the exports
     #[tracing::instrument(name = "refine", skip_all)]
-    pub fn refine(&mut self, work: &WorkPackage) -> Result<Vec<WorkDigest>> {
+    pub(crate) fn refine_on(
+        &mut self,
+        work: &WorkPackage,
+        core: u16,
+        auth_output: Vec<u8>,
+    ) -> Result<(Report, Vec<Segment>)> {
         tracing::debug!("package: items={}", work.items.len());
         if work.items.is_empty() {
             anyhow::bail!("no work items");
         }
 
+        self.chain.validate(&work.context)?;
+
+        let extrinsics = work
+            .items
+            .iter()
+            .map(|item| {
+                item.extrinsic
+                    .iter()
+                    .map(|spec| self.extrinsic(&spec.hash, spec.len))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let imports = work
+            .items
+            .iter()
+            .map(|item| self.segments.resolve(&item.import_segments))
+            .collect::<Result<Vec<_>>>()?;
+
         let mut result = Vec::new();
+        let mut exports = Vec::new();
+        let mut export_offset = 0;
+        let mut output_size = auth_output.len();
         for (index, item) in work.items.iter().enumerate() {
-            let refined = spacevm::refine(RefineArgs {
-                accounts: self.chain.accounts.clone(),
-                core: 0,
-                index: 0,
-                package: work.clone(),
-                export_offset: 0,
-                timeslot: self.chain.best.slot,
-                auth_output: Default::default(),
-                all_imports: Default::default(),
-            })?;
-
-            if !matches!(refined.executed.exec, WorkExecResult::Ok(_)) {
-                return Err(anyhow::anyhow!(
-                    "work item {index} refine failed: {:?}",
-                    refined.executed.exec
-                ));
+            let (backend, args) = (
+                self.backend,
+                RefineArgs {
+                    accounts: self.chain.accounts.clone(),
+                    core,
+                    index: index as _,
+                    package: work.clone(),
+                    export_offset,
+                    timeslot: self.chain.best.slot,
+                    auth_output: auth_output.clone(),
+                    all_imports: da::imports(&imports),
+                },
+            );
+            let extrinsics = extrinsics.clone();
+            let (refined, steps) = self.traced(|| self::refine(backend, args, extrinsics))?;
+
+            let mut exec = refined.executed.exec;
+            if let WorkExecResult::Ok(output) = &exec {
+                output_size += output.len();
+                if output_size > self.chain.config.constants.max_report_blobs as usize {
+                    exec = WorkExecResult::InvalidDigest;
+                }
+            }
+
+            if matches!(exec, WorkExecResult::Ok(_)) {
+                exports.extend(refined.segments);
+            } else {
+                // failed items export zeroed segments in place of their exports
+                tracing::warn!(
+                    "work item {index} refine failed: {exec:?}{}",
+                    self::dump(&steps)
+                );
+                exports.extend((0..item.export_count).map(|_| Segment([0; service::SEGMENT_SIZE])));
             }
 
+            export_offset += item.export_count;
             result.push(WorkDigest {
                 service_id: item.service,
                 code_hash: item.code_hash,
-                payload_hash: Default::default(),
-                accumulate_gas: Default::default(),
-                result: refined.executed.exec,
+                payload_hash: service::blake2b(&item.payload),
+                accumulate_gas: item.accumulate_gas_limit,
+                result: exec,
                 refine_load: RefineLoad {
                     gas_used: refined.executed.gas,
-                    imports: Default::default(),
-                    extrinsic_count: Default::default(),
-                    extrinsic_size: Default::default(),
-                    exports: Default::default(),
+                    imports: item.import_segments.len() as _,
+                    extrinsic_count: item.extrinsic.len() as _,
+                    extrinsic_size: item.extrinsic.iter().map(|spec| spec.len).sum(),
+                    exports: item.export_count,
                 },
             });
         }
 
-        Ok(result)
+        let package = util::package_hash(work)?;
+        let report = Report {
+            package,
+            core,
+            exports_root: da::segment_root(&exports),
+            authorizer_hash: auth::package_authorizer(work),
+            auth_output,
+            digests: result,
+            prerequisites: work.context.prerequisites.clone(),
+        };
+        Ok((report, exports))
     }
 
-    /// Accumulate the work package
+    /// Accumulate the work reports
     ///
-    /// 1. convert work package to work report
-    /// 2. run accumulate for all work items
-    /// 3. return the accumulated result
+    /// The reports with prerequisites which are not accumulated yet are
+    /// queued, and released once their prerequisites are accumulated. The
+    /// reports which exceed the gas budget of the block are deferred to the
+    /// next accumulation, ahead of its reports.
+    ///
+    /// 1. select the ready reports which fit in the block gas budget
+    /// 2. accumulate every service of the selected reports, the always
+    ///    accumulated services and the receivers of the deferred transfers
+    ///    against the same pre-state, in order of the service index, the
+    ///    received transfers precede the operands in the accumulate items
+    /// 3. repeat with the remaining reports and the new deferred transfers
+    ///
+    /// A service which does not halt keeps the state of its last checkpoint.
+    /// Returns the accumulation of every service in execution order.
     #[tracing::instrument(name = "accumulate", skip_all)]
-    pub fn accumulate(&mut self, results: Vec<WorkDigest>) -> Result<Vec<Accumulated>> {
-        tracing::debug!("work: items={}", results.len());
-        if results.is_empty() {
+    pub fn accumulate(&mut self, reports: Vec<Report>) -> Result<Vec<(ServiceId, Accumulated)>> {
+        tracing::debug!("work: reports={}", reports.len());
+        if reports.iter().all(|report| report.digests.is_empty()) {
             anyhow::bail!("no results");
         }
 
-        let accounts = self.chain.accounts.clone();
         let mut state = AccumulateState {
-            accounts,
-            validators: [ValidatorData {
-                bandersnatch: Default::default(),
-                ed25519: Default::default(),
-                bls: [0; 144],
-                metadata: [0; 128],
-            }; 6],
-            authorization: Default::default(),
-            privileges: Privileges::default(),
-            entropy: Default::default(),
+            accounts: self.chain.accounts.clone(),
+            validators: self.chain.config.validators,
+            authorization: self.chain.queues.clone(),
+            privileges: self.chain.privileges.clone(),
+            entropy: self.chain.entropy,
         };
 
-        let service = results.first().expect("checked").service_id;
-        let operands = {
-            let mut operands = vec![];
-            for work in results.iter() {
-                operands.push(Operand {
-                    package: Default::default(),
-                    exports_root: Default::default(),
-                    authorizer_hash: Default::default(),
-                    auth_output: Default::default(),
-                    payload: work.payload_hash,
-                    gas: work.accumulate_gas,
-                    data: work.result.clone(),
-                });
+        let mut always = state.privileges.always_acc.clone();
+        let mut gas =
+            self.chain.config.constants.total_accumulate_gas + always.values().sum::<u64>();
+        let mut pending = std::mem::take(&mut self.chain.deferred);
+        pending.extend(self.ready(reports));
+        let mut transfers = Vec::<DeferredTransfer>::new();
+        let mut accumulated = Vec::new();
+        let mut packages = Vec::new();
+        loop {
+            // select the reports which fit in the remaining gas
+            let mut used = 0;
+            let selected = pending
+                .iter()
+                .take_while(|report| {
+                    used += report.gas();
+                    used <= gas
+                })
+                .count();
+            let reports = pending.drain(..selected).collect::<Vec<_>>();
+            if reports.is_empty() && transfers.is_empty() && always.is_empty() {
+                break;
             }
-            operands
-        };
 
-        // run accumulation
-        let accumulated = spacevm::accumulate(AccumulateArgs {
-            context: state,
-            timeslot: self.chain.best.slot,
-            service,
-            gas: 1_000_000_000,
-            operands,
-        })?;
+            packages.extend(reports.iter().map(|report| report.package));
+            gas += transfers
+                .iter()
+                .map(|transfer| transfer.gas_limit)
+                .sum::<u64>();
+            let round = self.accumulate_round(&mut state, reports, transfers, always)?;
+            transfers = Vec::new();
+            always = BTreeMap::new();
+            for (service, result) in round {
+                gas = gas.saturating_sub(result.gas);
+                transfers.extend(result.transfers.clone());
+                accumulated.push((service, result));
+            }
+        }
 
-        if !matches!(accumulated.reason, Reason::Halt) {
-            anyhow::bail!("accumulate failed: {:?}", accumulated.reason);
+        if !pending.is_empty() {
+            tracing::warn!("{} reports deferred for the accumulate gas", pending.len());
+            self.chain.deferred = pending;
         }
-        state = accumulated.context.clone();
+
         self.chain.accounts = state.accounts;
-        Ok(vec![accumulated])
+        self.chain.queues = state.authorization;
+        self.chain.privileges = state.privileges;
+        self.chain.accumulated.extend(packages);
+        Ok(accumulated)
+    }
+
+    /// Get the reports queued for their prerequisites
+    pub fn queued(&self) -> &[Report] {
+        &self.chain.ready
+    }
+
+    /// Get the reports deferred to the next accumulation for the gas budget
+    pub fn deferred(&self) -> &[Report] {
+        &self.chain.deferred
+    }
+
+    /// Queue the reports with unaccumulated prerequisites, returns the
+    /// reports which are ready to accumulate in dependency order
+    fn ready(&mut self, reports: Vec<Report>) -> Vec<Report> {
+        let (mut ready, queued): (Vec<_>, Vec<_>) = reports.into_iter().partition(|report| {
+            report
+                .prerequisites
+                .iter()
+                .all(|package| self.chain.accumulated.contains(package))
+        });
+        self.chain.ready.extend(queued);
+
+        // release the queued reports whose prerequisites are accumulated
+        // or accumulated ahead of them
+        loop {
+            let done = ready
+                .iter()
+                .map(|report| report.package)
+                .collect::<BTreeSet<_>>();
+            let (released, queued): (Vec<_>, Vec<_>) = std::mem::take(&mut self.chain.ready)
+                .into_iter()
+                .partition(|report| {
+                    report.prerequisites.iter().all(|package| {
+                        done.contains(package) || self.chain.accumulated.contains(package)
+                    })
+                });
+            self.chain.ready = queued;
+            if released.is_empty() {
+                break;
+            }
+
+            ready.extend(released);
+        }
+
+        if !self.chain.ready.is_empty() {
+            tracing::debug!(
+                "{} reports queued for prerequisites",
+                self.chain.ready.len()
+            );
+        }
+        ready
+    }
+
+    /// Accumulate the services of a round against the same pre-state
+    fn accumulate_round(
+        &mut self,
+        state: &mut AccumulateState,
+        reports: Vec<Report>,
+        transfers: Vec<DeferredTransfer>,
+        always: BTreeMap<ServiceId, u64>,
+    ) -> Result<Vec<(ServiceId, Accumulated)>> {
+        let mut services = always;
+        for digest in reports.iter().flat_map(|report| report.digests.iter()) {
+            *services.entry(digest.service_id).or_default() += digest.accumulate_gas;
+        }
+
+        // deliver the deferred transfers, the transfers to the services
+        // removed since they were sent are returned to the sender
+        let mut received = BTreeMap::<ServiceId, Vec<DeferredTransfer>>::new();
+        for transfer in transfers {
+            let recipient = if state.accounts.contains_key(&transfer.recipient) {
+                *services.entry(transfer.recipient).or_default() += transfer.gas_limit;
+                received
+                    .entry(transfer.recipient)
+                    .or_default()
+                    .push(transfer.clone());
+                transfer.recipient
+            } else {
+                tracing::warn!(
+                    "service {} not found, returning the transfer to service {}",
+                    transfer.recipient,
+                    transfer.sender
+                );
+                transfer.sender
+            };
+
+            let Some(account) = state.accounts.get_mut(&recipient) else {
+                tracing::warn!("service {recipient} not found, dropping the transfer");
+                continue;
+            };
+
+            account.info.balance = account
+                .info
+                .balance
+                .checked_add(transfer.amount)
+                .ok_or_else(|| anyhow!("balance of service {recipient} overflows"))?;
+        }
+
+        let pre = state.clone();
+        let mut result = Vec::new();
+        for (service, gas) in services {
+            let operands = reports
+                .iter()
+                .flat_map(|report| report.operands(service))
+                .collect();
+
+            let (backend, args, transfers) = (
+                self.backend,
+                AccumulateArgs {
+                    context: pre.clone(),
+                    timeslot: self.chain.best.slot,
+                    service,
+                    gas,
+                    operands,
+                },
+                received.remove(&service).unwrap_or_default(),
+            );
+            let (accumulated, steps) =
+                self.traced(|| self::accumulate(backend, args, transfers))?;
+            if !matches!(accumulated.reason, Reason::Halt) {
+                tracing::warn!(
+                    "service {service} accumulate failed: {:?}{}",
+                    accumulated.reason,
+                    self::dump(&steps)
+                );
+            }
+
+            self::merge(state, &pre, service, &accumulated.context);
+            result.push((service, accumulated));
+        }
+
+        Ok(result)
+    }
+
+    /// Get the data of an extrinsic
+    fn extrinsic(&self, hash: &OpaqueHash, len: u32) -> Result<Vec<u8>> {
+        self.extrinsics
+            .iter()
+            .find(|extrinsic| extrinsic.hash == *hash && extrinsic.len == len)
+            .map(|extrinsic| extrinsic.extrinsic.clone())
+            .ok_or_else(|| anyhow::anyhow!("extrinsic 0x{} not found", hex::encode(hash)))
+    }
+
+    /// Take the gas profile of the invocations since the last take
+    pub fn take_profile(&mut self) -> Profile {
+        std::mem::take(&mut self.profile)
+    }
+
+    /// Run an invocation with the configured constants, tracing and profiling
+    ///
+    /// The tracing state of the thread, e.g. a hook installed with
+    /// [`trace::on_step`], is restored after the invocation.
+    fn traced<T>(&mut self, call: impl FnOnce() -> Result<T>) -> Result<(T, Vec<Step>)> {
+        if self.trace == 0 {
+            return self.observed(call).map(|result| (result, Vec::new()));
+        }
+
+        let capacity = self.trace;
+        let (result, steps) = trace::capture(capacity, || self.observed(call))?;
+        result.map(|result| (result, steps))
+    }
+
+    /// Run an invocation with the configured constants and profiling
+    ///
+    /// The logs of the guest are captured as well.
+    fn observed<T>(&mut self, call: impl FnOnce() -> Result<T>) -> Result<T> {
+        constants::configure(self.chain.config.constants);
+        logs::start();
+        if let Some(symbols) = &self.symbols {
+            profile::start(symbols.clone());
+        }
+
+        let result = call();
+        self.logs.extend(logs::stop());
+        if self.symbols.is_some() {
+            self.profile.merge(profile::stop());
+        }
+
+        result
+    }
+}
+
+/// Refine a work item with the extrinsics of the package
+#[cfg(feature = "pure")]
+fn refine(backend: Backend, args: RefineArgs, extrinsics: Vec<Vec<Vec<u8>>>) -> Result<Refined> {
+    spacevm::refine_with_extrinsics(backend, args, extrinsics)
+}
+
+/// Refine a work item, the native backend serves no extrinsics
+#[cfg(not(feature = "pure"))]
+fn refine(backend: Backend, args: RefineArgs, extrinsics: Vec<Vec<Vec<u8>>>) -> Result<Refined> {
+    if extrinsics.iter().any(|item| !item.is_empty()) {
+        return Err(spacevm::Error::Unsupported {
+            call: "refine",
+            feature: "extrinsics",
+        }
+        .into());
+    }
+
+    spacevm::refine_with(backend, args)
+}
+
+/// Accumulate a service with the deferred transfers it received
+#[cfg(feature = "pure")]
+fn accumulate(
+    backend: Backend,
+    args: AccumulateArgs,
+    transfers: Vec<DeferredTransfer>,
+) -> Result<Accumulated> {
+    spacevm::accumulate_with_transfers(backend, args, transfers)
+}
+
+/// Accumulate a service, the native backend serves no deferred transfers
+#[cfg(not(feature = "pure"))]
+fn accumulate(
+    backend: Backend,
+    args: AccumulateArgs,
+    transfers: Vec<DeferredTransfer>,
+) -> Result<Accumulated> {
+    if !transfers.is_empty() {
+        return Err(spacevm::Error::Unsupported {
+            call: "accumulate",
+            feature: "deferred transfers",
+        }
+        .into());
+    }
+
+    spacevm::accumulate_with(backend, args)
+}
+
+/// Format the traced steps for the error reports
+fn dump(steps: &[Step]) -> String {
+    if steps.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("\nlast {} steps:", steps.len());
+    for step in steps {
+        out.push_str(&format!("\n{step}"));
+    }
+    out
+}
+
+/// Merge the post-state of a service accumulation into the round state
+///
+/// The service owns its own account and the accounts it created or removed,
+/// the privileged services own the privileges, validators and queues.
+fn merge(
+    state: &mut AccumulateState,
+    pre: &AccumulateState,
+    service: ServiceId,
+    post: &AccumulateState,
+) {
+    for (id, account) in post.accounts.iter() {
+        if *id == service || !pre.accounts.contains_key(id) {
+            state.accounts.insert(*id, account.clone());
+        }
+    }
+
+    for id in pre.accounts.keys() {
+        if !post.accounts.contains_key(id) {
+            state.accounts.remove(id);
+        }
+    }
+
+    if pre.privileges.bless == service {
+        state.privileges = post.privileges.clone();
+    }
+
+    if pre.privileges.designate == service {
+        state.validators = post.validators;
+    }
+
+    for (core, assigner) in pre.privileges.assign.iter().enumerate() {
+        if *assigner == service {
+            state.authorization[core] = post.authorization[core].clone();
+            state.privileges.assign[core] = post.privileges.assign[core];
+        }
     }
 }
@@ -3,6 +3,7 @@
 use service::OpaqueHash;
 
 /// Extrinsic context
+#[derive(Clone, Debug, Default)]
 pub struct Extrinsic {
     /// The extrinsic
     pub extrinsic: Vec<u8>,
@@ -13,3 +14,14 @@ pub struct Extrinsic {
     /// The extrinsic length
     pub len: u32,
 }
+
+impl Extrinsic {
+    /// Create an extrinsic from its data
+    pub fn new(extrinsic: Vec<u8>) -> Self {
+        Self {
+            hash: service::blake2b(&extrinsic),
+            len: extrinsic.len() as u32,
+            extrinsic,
+        }
+    }
+}
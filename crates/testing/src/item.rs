@@ -0,0 +1,141 @@
+//! Work item builder
+
+use crate::{Extrinsic, Jam};
+use anyhow::Result;
+use service::{
+    OpaqueHash, ServiceId,
+    service::{ExtrinsicSpec, ImportSpec, WorkItem},
+};
+
+/// The default refine and accumulate gas limit of the work items
+const DEFAULT_GAS_LIMIT: u64 = 1_000_000;
+
+/// Default gas limits of the work items
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GasLimits {
+    /// The refine gas limit
+    pub refine: u64,
+
+    /// The accumulate gas limit
+    pub accumulate: u64,
+}
+
+impl Default for GasLimits {
+    fn default() -> Self {
+        Self {
+            refine: DEFAULT_GAS_LIMIT,
+            accumulate: DEFAULT_GAS_LIMIT,
+        }
+    }
+}
+
+/// Work item builder
+#[derive(Clone, Debug)]
+pub struct WorkItemBuilder {
+    /// The work item
+    item: WorkItem,
+
+    /// The extrinsics of the work item
+    extrinsics: Vec<Extrinsic>,
+}
+
+impl WorkItemBuilder {
+    /// Create a work item of a service with the default gas limits
+    pub fn new(service: ServiceId, code_hash: OpaqueHash, payload: Vec<u8>) -> Self {
+        let limits = GasLimits::default();
+        Self {
+            item: WorkItem {
+                service,
+                code_hash,
+                payload,
+                refine_gas_limit: limits.refine,
+                accumulate_gas_limit: limits.accumulate,
+                export_count: 0,
+                import_segments: Vec::new(),
+                extrinsic: Vec::new(),
+            },
+            extrinsics: Vec::new(),
+        }
+    }
+
+    /// Set the refine gas limit
+    pub fn with_refine_gas(mut self, gas: u64) -> Self {
+        self.item.refine_gas_limit = gas;
+        self
+    }
+
+    /// Set the accumulate gas limit
+    pub fn with_accumulate_gas(mut self, gas: u64) -> Self {
+        self.item.accumulate_gas_limit = gas;
+        self
+    }
+
+    /// Set the number of segments exported by refine
+    pub fn with_export_count(mut self, count: u16) -> Self {
+        self.item.export_count = count;
+        self
+    }
+
+    /// Import a segment, see [`SegmentStore::import`](crate::SegmentStore::import)
+    pub fn with_import(mut self, import: ImportSpec) -> Self {
+        self.item.import_segments.push(import);
+        self
+    }
+
+    /// Import a segment exported by a package by the package hash
+    pub fn with_package_import(mut self, package: OpaqueHash, index: u16) -> Self {
+        self.item.import_segments.push(ImportSpec {
+            tree_root: package,
+            index,
+        });
+        self
+    }
+
+    /// Import the segments
+    pub fn with_imports(mut self, imports: Vec<ImportSpec>) -> Self {
+        self.item.import_segments.extend(imports);
+        self
+    }
+
+    /// Add an extrinsic, served to refine by the pure backend only
+    ///
+    /// The native backend fails to refine the item with
+    /// [`spacevm::Error::Unsupported`].
+    pub fn with_extrinsic(mut self, extrinsic: Vec<u8>) -> Self {
+        let extrinsic = Extrinsic::new(extrinsic);
+        self.item.extrinsic.push(ExtrinsicSpec {
+            hash: extrinsic.hash,
+            len: extrinsic.len,
+        });
+        self.extrinsics.push(extrinsic);
+        self
+    }
+
+    /// Build the work item with its extrinsics
+    pub fn build(self) -> (WorkItem, Vec<Extrinsic>) {
+        (self.item, self.extrinsics)
+    }
+}
+
+impl Jam {
+    /// Set the default gas limits of the packed work items
+    pub fn with_gas_limits(mut self, refine: u64, accumulate: u64) -> Self {
+        self.limits = GasLimits { refine, accumulate };
+        self
+    }
+
+    /// Create a work item of a service with the default gas limits
+    pub fn item(&self, service: ServiceId, payload: Vec<u8>) -> Result<WorkItemBuilder> {
+        let code_hash = self.chain.service(service)?;
+        Ok(WorkItemBuilder::new(service, code_hash, payload)
+            .with_refine_gas(self.limits.refine)
+            .with_accumulate_gas(self.limits.accumulate))
+    }
+
+    /// Pack a built work item with its extrinsics
+    pub fn pack_item(&mut self, item: WorkItemBuilder) {
+        let (item, extrinsics) = item.build();
+        self.extrinsics.extend(extrinsics);
+        self.add_item(item);
+    }
+}
@@ -2,20 +2,51 @@
 #![deny(missing_docs)]
 
 pub use service::service::ServiceAccount as Account;
-use service::service::WorkItem;
-pub use {auth::Auth, chain::Chain, extrinsic::Extrinsic};
+use service::{
+    OpaqueHash,
+    service::{WorkItem, WorkPackage},
+};
+pub use spacevm::{
+    Backend,
+    constants::Constants,
+    logs::Log,
+    profile::{Profile, Symbols},
+};
+use std::collections::BTreeMap;
+pub use {
+    auth::Auth,
+    chain::{Chain, Head},
+    config::ChainConfig,
+    da::SegmentStore,
+    diff::{AccountDiff, Change, StateDiff},
+    exec::{Accumulation, ExecutionInfo, Report, WorkResult},
+    extrinsic::Extrinsic,
+    item::WorkItemBuilder,
+    snapshot::Snapshot,
+    storage::{Render, Storage, StorageEntry, TrackedKeys},
+};
 
 mod account;
 mod auth;
+mod block;
 mod builder;
 mod chain;
+mod config;
+mod da;
+mod diff;
 mod exec;
 mod extrinsic;
+mod item;
 pub mod key;
+#[doc(hidden)]
+pub mod macros;
+mod snapshot;
+mod state;
+mod storage;
 pub mod util;
 
 /// JAM environment
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Jam {
     /// Chain environment
     chain: Chain,
@@ -26,6 +57,79 @@ pub struct Jam {
     /// work items
     items: Vec<WorkItem>,
 
-    /// extrinsics
-    _extrinsic: Vec<Extrinsic>,
+    /// prerequisites of the next work package
+    prerequisites: Vec<OpaqueHash>,
+
+    /// extrinsics of the work items
+    extrinsics: Vec<Extrinsic>,
+
+    /// work packages submitted for the next block by core
+    submitted: BTreeMap<u16, WorkPackage>,
+
+    /// tracked original storage keys
+    keys: TrackedKeys,
+
+    /// exported segments of the refined packages
+    segments: SegmentStore,
+
+    /// execution backend
+    backend: Backend,
+
+    /// default gas limits of the work items
+    limits: item::GasLimits,
+
+    /// core of the work packages
+    core: u16,
+
+    /// number of traced steps to dump on failures
+    trace: usize,
+
+    /// function symbols of the profiled services
+    symbols: Option<Symbols>,
+
+    /// gas profile of the invocations
+    profile: Profile,
+
+    /// logs of the guests since the last execution
+    logs: Vec<Log>,
+}
+
+impl Jam {
+    /// Set the execution backend
+    ///
+    /// Defaults to the global backend, which can be selected with the
+    /// `SPACEVM_BACKEND` environment variable.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Get the exported segments of the refined packages
+    pub fn segments(&self) -> &SegmentStore {
+        &self.segments
+    }
+
+    /// Set the core the work packages are refined on
+    pub fn with_core(mut self, core: u16) -> Self {
+        self.core = core;
+        self
+    }
+
+    /// Dump the last `steps` executed instructions when an invocation fails
+    ///
+    /// The steps are only recorded by the `pure` backend, the invocations
+    /// fail on the native one.
+    pub fn with_trace(mut self, steps: usize) -> Self {
+        self.trace = steps;
+        self
+    }
+
+    /// Profile the gas of the invocations, naming the functions with `symbols`
+    ///
+    /// The gas is only profiled by the `pure` backend, see
+    /// [`util::load_symbols`] for the symbols of a service.
+    pub fn with_profile(mut self, symbols: Symbols) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
 }
@@ -0,0 +1,239 @@
+//! Assertion macros of the execution results
+//!
+//! The failure messages of the state assertions describe the state diff of
+//! the execution.
+
+use crate::Storage;
+
+/// Decode a storage value as the type of the expected value
+pub fn storage_value<V: serde::de::DeserializeOwned>(
+    storage: Storage<'_>,
+    key: &[u8],
+    _expected: &V,
+) -> Option<V> {
+    storage.get(key)
+}
+
+/// Assert the refine result of a work item, the first item by default
+#[macro_export]
+macro_rules! assert_work_result {
+    ($info:expr, $result:pat) => {
+        $crate::assert_work_result!($info, 0, $result)
+    };
+    ($info:expr, $item:expr, $result:pat) => {{
+        let result = $info.result($item);
+        assert!(
+            matches!(result, Some($result)),
+            "work item {} refined to {:?}, expected {}",
+            $item,
+            result,
+            stringify!($result),
+        );
+    }};
+}
+
+/// Assert a work item ran out of gas in refine, the first item by default
+#[macro_export]
+macro_rules! assert_out_of_gas {
+    ($info:expr) => {
+        $crate::assert_out_of_gas!($info, 0)
+    };
+    ($info:expr, $item:expr) => {{
+        let result = $info.result($item);
+        assert!(
+            matches!(result, Some($crate::WorkResult::OutOfGas)),
+            "work item {} refined to {:?}, expected out of gas",
+            $item,
+            result,
+        );
+    }};
+}
+
+/// Assert a storage value of a service equals the expected value
+#[macro_export]
+macro_rules! assert_storage {
+    ($info:expr, $service:expr, $key:expr, $expected:expr) => {{
+        let info = &$info;
+        let expected = $expected;
+        let key: &[u8] = $key.as_ref();
+        let actual = $crate::macros::storage_value(info.storage($service), key, &expected);
+        assert!(
+            actual.as_ref() == Some(&expected),
+            "storage {:?} of service {} is {:?}, expected {:?}\nstate diff:\n{}",
+            String::from_utf8_lossy(key),
+            $service,
+            actual,
+            expected,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert a storage key of a service is absent
+#[macro_export]
+macro_rules! assert_storage_absent {
+    ($info:expr, $service:expr, $key:expr) => {{
+        let info = &$info;
+        let key: &[u8] = $key.as_ref();
+        assert!(
+            !info.storage($service).contains(key),
+            "storage {:?} of service {} is present\nstate diff:\n{}",
+            String::from_utf8_lossy(key),
+            $service,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert the balance of a service changed by a delta
+#[macro_export]
+macro_rules! assert_balance_changed {
+    ($info:expr, $service:expr, $delta:expr) => {{
+        let info = &$info;
+        let delta = $delta as i128;
+        let actual = info
+            .diff
+            .account($service)
+            .map_or(0, |account| account.balance);
+        assert!(
+            actual == delta,
+            "balance of service {} changed by {:+}, expected {:+}\nstate diff:\n{}",
+            $service,
+            actual,
+            delta,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert a service is created, or any service without the id
+#[macro_export]
+macro_rules! assert_service_created {
+    ($info:expr) => {{
+        let info = &$info;
+        assert!(
+            !info.diff.created.is_empty(),
+            "no service created\nstate diff:\n{}",
+            info.describe(),
+        );
+    }};
+    ($info:expr, $service:expr) => {{
+        let info = &$info;
+        assert!(
+            info.diff.created.contains(&$service),
+            "service {} is not created\nstate diff:\n{}",
+            $service,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert a deferred transfer is sent to a destination, optionally with
+/// the amount
+#[macro_export]
+macro_rules! assert_transfer {
+    ($info:expr, $to:expr) => {{
+        let info = &$info;
+        let to = $to;
+        let transfers = info
+            .services
+            .values()
+            .flat_map(|accumulation| accumulation.transfers.iter())
+            .collect::<Vec<_>>();
+        assert!(
+            transfers.iter().any(|transfer| transfer.recipient == to),
+            "no transfer sent to {}, transfers: {:?}\nstate diff:\n{}",
+            to,
+            transfers,
+            info.describe(),
+        );
+    }};
+    ($info:expr, $to:expr, $amount:expr) => {{
+        let info = &$info;
+        let (to, amount) = ($to, $amount);
+        let transfers = info
+            .services
+            .values()
+            .flat_map(|accumulation| accumulation.transfers.iter())
+            .collect::<Vec<_>>();
+        assert!(
+            transfers
+                .iter()
+                .any(|transfer| transfer.recipient == to && transfer.amount == amount),
+            "no transfer of {} sent to {}, transfers: {:?}\nstate diff:\n{}",
+            amount,
+            to,
+            transfers,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert the accumulation output of a service equals a hash
+#[macro_export]
+macro_rules! assert_output {
+    ($info:expr, $service:expr, $hash:expr) => {{
+        let info = &$info;
+        let expected = $hash;
+        let actual = info
+            .service($service)
+            .and_then(|accumulation| accumulation.output);
+        assert!(
+            actual == Some(expected),
+            "output of service {} is {:?}, expected {:?}\nstate diff:\n{}",
+            $service,
+            actual,
+            expected,
+            info.describe(),
+        );
+    }};
+}
+
+/// Assert the gas used is within a range, the refine and accumulate gas
+/// together or one of them
+#[macro_export]
+macro_rules! assert_gas {
+    ($info:expr, refine, $range:expr) => {{
+        let info = &$info;
+        $crate::assert_gas!(@check "refine", info.refine_gas, $range)
+    }};
+    ($info:expr, accumulate, $range:expr) => {{
+        let info = &$info;
+        $crate::assert_gas!(@check "accumulate", info.accumulate_gas, $range)
+    }};
+    ($info:expr, $range:expr) => {{
+        let info = &$info;
+        $crate::assert_gas!(@check "total", info.refine_gas + info.accumulate_gas, $range)
+    }};
+    (@check $name:literal, $gas:expr, $range:expr) => {{
+        let (gas, range) = ($gas, $range);
+        assert!(
+            range.contains(&gas),
+            "{} gas {} is not within {:?}",
+            $name,
+            gas,
+            range,
+        );
+    }};
+}
+
+/// Assert a guest log contains a string, the logs are only captured by the
+/// `pure` backend
+#[cfg(feature = "pure")]
+#[macro_export]
+macro_rules! assert_log {
+    ($info:expr, $needle:expr) => {{
+        let info = &$info;
+        let needle: &str = $needle.as_ref();
+        assert!(
+            info.logs.iter().any(|log| log.message.contains(needle)),
+            "no guest log contains {:?}, logs:\n{}",
+            needle,
+            info.logs
+                .iter()
+                .map(|log| log.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }};
+}
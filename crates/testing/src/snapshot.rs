@@ -0,0 +1,69 @@
+//! Snapshots of the test state
+
+use crate::{Auth, Chain, Extrinsic, Jam, SegmentStore};
+use service::{
+    OpaqueHash,
+    service::{WorkItem, WorkPackage},
+};
+use std::collections::BTreeMap;
+
+/// A snapshot of the test state
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    /// Chain environment
+    chain: Chain,
+
+    /// authorization token
+    auth: Auth,
+
+    /// work items
+    items: Vec<WorkItem>,
+
+    /// prerequisites of the next work package
+    prerequisites: Vec<OpaqueHash>,
+
+    /// submitted work packages
+    submitted: BTreeMap<u16, WorkPackage>,
+
+    /// extrinsics
+    extrinsic: Vec<Extrinsic>,
+
+    /// exported segments
+    segments: SegmentStore,
+}
+
+impl Jam {
+    /// Take a snapshot of the chain, the authorization and the pending work
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            chain: self.chain.clone(),
+            auth: self.auth.clone(),
+            items: self.items.clone(),
+            prerequisites: self.prerequisites.clone(),
+            submitted: self.submitted.clone(),
+            extrinsic: self.extrinsics.clone(),
+            segments: self.segments.clone(),
+        }
+    }
+
+    /// Restore a snapshot, the execution options are kept
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.chain = snapshot.chain.clone();
+        self.auth = snapshot.auth.clone();
+        self.items = snapshot.items.clone();
+        self.prerequisites = snapshot.prerequisites.clone();
+        self.submitted = snapshot.submitted.clone();
+        self.extrinsics = snapshot.extrinsic.clone();
+        self.segments = snapshot.segments.clone();
+    }
+
+    /// Fork the environment into an independent one
+    ///
+    /// The fork starts with an empty gas profile.
+    pub fn fork(&self) -> Self {
+        Self {
+            profile: Default::default(),
+            ..self.clone()
+        }
+    }
+}
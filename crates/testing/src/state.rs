@@ -0,0 +1,336 @@
+//! Chain state fixtures
+//!
+//! The state is saved as JSON with hex-encoded keys and values if the path
+//! ends with `.json`, otherwise in the binary codec form.
+
+use crate::{Jam, chain::Head};
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use service::{
+    CORES_COUNT, EntropyBuffer, OpaqueHash, ServiceId,
+    service::{Privileges, ServiceAccount, account::ServiceInfo},
+};
+use std::{collections::BTreeMap, path::Path};
+
+/// The chain state in the binary form
+#[derive(Serialize, Deserialize)]
+struct State {
+    /// Best block
+    best: Head,
+
+    /// Finalized block
+    finalized: Head,
+
+    /// Entropy buffer
+    entropy: EntropyBuffer,
+
+    /// Seed of the entropy
+    seed: u64,
+
+    /// Service accounts
+    accounts: BTreeMap<ServiceId, ServiceAccount>,
+
+    /// Privileged services
+    privileges: Privileges,
+
+    /// Authorizer pools by core
+    pools: [Vec<OpaqueHash>; CORES_COUNT],
+
+    /// Authorizer queues by core
+    queues: [Vec<OpaqueHash>; CORES_COUNT],
+}
+
+/// The chain state in the JSON form
+#[derive(Serialize, Deserialize)]
+struct JsonState {
+    /// Best block
+    best: JsonHead,
+
+    /// Finalized block
+    finalized: JsonHead,
+
+    /// Entropy buffer
+    entropy: Vec<String>,
+
+    /// Seed of the entropy
+    seed: u64,
+
+    /// Service accounts
+    accounts: BTreeMap<ServiceId, JsonAccount>,
+
+    /// Privileged services
+    privileges: Privileges,
+
+    /// Authorizer pools by core
+    pools: Vec<Vec<String>>,
+
+    /// Authorizer queues by core
+    queues: Vec<Vec<String>>,
+}
+
+/// A block head in the JSON form
+#[derive(Serialize, Deserialize)]
+struct JsonHead {
+    /// Hash of the block
+    hash: String,
+
+    /// Slot of the block
+    slot: u32,
+
+    /// State root of the block
+    state_root: String,
+
+    /// Beefy root of the block
+    beefy_root: String,
+
+    /// Accumulated entropy after the block
+    entropy: String,
+}
+
+/// A service account in the JSON form
+#[derive(Serialize, Deserialize)]
+struct JsonAccount {
+    /// Service info, including the code hash and the balance
+    info: ServiceInfo,
+
+    /// Storage by the storage key
+    storage: BTreeMap<String, String>,
+
+    /// Preimages by their hash
+    preimages: BTreeMap<String, String>,
+
+    /// Lookup histories by `<hash>:<length>`
+    lookup: BTreeMap<String, Vec<u32>>,
+}
+
+impl Jam {
+    /// Save the chain state to a file
+    ///
+    /// Saved as JSON if the path ends with `.json`, otherwise as binary.
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let chain = &self.chain;
+        let state = State {
+            best: chain.best.clone(),
+            finalized: chain.finalized.clone(),
+            entropy: chain.entropy,
+            seed: chain.seed,
+            accounts: chain.accounts.clone(),
+            privileges: chain.privileges.clone(),
+            pools: chain.pools.clone(),
+            queues: chain.queues.clone(),
+        };
+
+        let encoded = if self::is_json(path) {
+            serde_json::to_vec_pretty(&JsonState::from(state))?
+        } else {
+            codec::encode(&state)?
+        };
+
+        std::fs::write(path, encoded).context(format!("Failed to write {}", path.display()))
+    }
+
+    /// Load the chain state from a file saved by [`Jam::save_state`]
+    ///
+    /// The state is merged into the chain, the config, the accumulated
+    /// packages and the recent blocks are kept. The loaded best block becomes
+    /// the most recent one.
+    pub fn load_state(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let encoded = std::fs::read(path).context(format!("Failed to read {}", path.display()))?;
+        let state = if self::is_json(path) {
+            serde_json::from_slice::<JsonState>(&encoded)?.try_into()?
+        } else {
+            codec::decode::<State>(&encoded)?
+        };
+
+        let chain = &mut self.chain;
+        chain.best = state.best;
+        chain.finalized = state.finalized;
+        chain.entropy = state.entropy;
+        chain.seed = state.seed;
+        chain.accounts = state.accounts;
+        chain.privileges = state.privileges;
+        chain.pools = state.pools;
+        chain.queues = state.queues;
+        if chain.recent.back() != Some(&chain.best) {
+            chain.recent.push_back(chain.best.clone());
+            while chain.recent.len() > chain.config.constants.recent_history as usize {
+                chain.recent.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an environment from a state file
+    pub fn with_state(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.load_state(path)?;
+        Ok(self)
+    }
+}
+
+impl From<State> for JsonState {
+    fn from(state: State) -> Self {
+        let accounts = state
+            .accounts
+            .into_iter()
+            .map(|(service, account)| {
+                let account = JsonAccount {
+                    info: account.info,
+                    storage: account
+                        .storage
+                        .iter()
+                        .map(|(key, value)| (self::hex(key), self::hex(value)))
+                        .collect(),
+                    preimages: account
+                        .preimage
+                        .iter()
+                        .map(|(hash, preimage)| (self::hex(hash), self::hex(preimage)))
+                        .collect(),
+                    lookup: account
+                        .lookup
+                        .into_iter()
+                        .map(|((hash, len), history)| {
+                            (format!("{}:{len}", self::hex(&hash)), history)
+                        })
+                        .collect(),
+                };
+                (service, account)
+            })
+            .collect();
+
+        Self {
+            best: JsonHead::from(state.best),
+            finalized: JsonHead::from(state.finalized),
+            entropy: state.entropy.iter().map(|hash| self::hex(hash)).collect(),
+            seed: state.seed,
+            accounts,
+            privileges: state.privileges,
+            pools: self::hexes(&state.pools),
+            queues: self::hexes(&state.queues),
+        }
+    }
+}
+
+impl TryFrom<JsonState> for State {
+    type Error = anyhow::Error;
+
+    fn try_from(state: JsonState) -> Result<Self> {
+        let mut accounts = BTreeMap::new();
+        for (service, json) in state.accounts {
+            let mut account = ServiceAccount {
+                index: service,
+                info: json.info,
+                ..Default::default()
+            };
+
+            for (key, value) in json.storage {
+                account
+                    .storage
+                    .insert(self::unhex(&key)?, self::unhex(&value)?);
+            }
+
+            for (hash, preimage) in json.preimages {
+                account
+                    .preimage
+                    .insert(self::hash(&hash)?, self::unhex(&preimage)?);
+            }
+
+            for (key, history) in json.lookup {
+                let (hash, len) = key
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("invalid lookup key {key}"))?;
+                account
+                    .lookup
+                    .insert((self::hash(hash)?, len.parse()?), history);
+            }
+
+            accounts.insert(service, account);
+        }
+
+        Ok(Self {
+            best: state.best.try_into()?,
+            finalized: state.finalized.try_into()?,
+            entropy: self::array(&state.entropy, "entropy buffer", |hash| self::hash(hash))?,
+            seed: state.seed,
+            accounts,
+            privileges: state.privileges,
+            pools: self::array(&state.pools, "authorizer pools", |pool| {
+                pool.iter().map(|hash| self::hash(hash)).collect()
+            })?,
+            queues: self::array(&state.queues, "authorizer queues", |queue| {
+                queue.iter().map(|hash| self::hash(hash)).collect()
+            })?,
+        })
+    }
+}
+
+impl From<Head> for JsonHead {
+    fn from(head: Head) -> Self {
+        Self {
+            hash: self::hex(&head.hash),
+            slot: head.slot,
+            state_root: self::hex(&head.state_root),
+            beefy_root: self::hex(&head.beefy_root),
+            entropy: self::hex(&head.entropy),
+        }
+    }
+}
+
+impl TryFrom<JsonHead> for Head {
+    type Error = anyhow::Error;
+
+    fn try_from(head: JsonHead) -> Result<Self> {
+        Ok(Self {
+            hash: self::hash(&head.hash)?,
+            slot: head.slot,
+            state_root: self::hash(&head.state_root)?,
+            beefy_root: self::hash(&head.beefy_root)?,
+            entropy: self::hash(&head.entropy)?,
+        })
+    }
+}
+
+/// If the state file is JSON
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Encode bytes as `0x` prefixed hex
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Decode `0x` prefixed hex
+fn unhex(hex: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(hex.trim_start_matches("0x"))?)
+}
+
+/// Encode the hashes of the cores as hex
+fn hexes(cores: &[Vec<OpaqueHash>]) -> Vec<Vec<String>> {
+    cores
+        .iter()
+        .map(|hashes| hashes.iter().map(|hash| self::hex(hash)).collect())
+        .collect()
+}
+
+/// Decode a fixed number of entries, rejecting any other count
+fn array<T, U, const N: usize>(
+    entries: &[T],
+    name: &str,
+    decode: impl Fn(&T) -> Result<U>,
+) -> Result<[U; N]> {
+    if entries.len() != N {
+        anyhow::bail!("invalid {name} of {} entries, expected {N}", entries.len());
+    }
+
+    let decoded = entries.iter().map(decode).collect::<Result<Vec<_>>>()?;
+    decoded.try_into().map_err(|_| anyhow!("invalid {name}"))
+}
+
+/// Decode a hex hash
+fn hash(hex: &str) -> Result<OpaqueHash> {
+    self::unhex(hex)?
+        .try_into()
+        .map_err(|_| anyhow!("invalid hash {hex}"))
+}
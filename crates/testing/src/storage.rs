@@ -0,0 +1,187 @@
+//! Storage inspection of the service accounts
+//!
+//! Storage keys are hashed from the raw key bytes, the same way the guest
+//! `storage::write` does. The original keys are only known for the keys
+//! tracked with [`Jam::track_storage_key`], and the values are decoded for
+//! the keys tracked with [`Jam::track_storage`].
+
+use crate::{ExecutionInfo, Jam, diff, key};
+use serde::de::DeserializeOwned;
+use service::{ServiceId, service::ServiceAccount};
+use std::{collections::BTreeMap, fmt};
+
+/// Render a storage value in the descriptions
+pub type Render = fn(&[u8]) -> String;
+
+/// The tracked original storage keys with the renderers of their values
+pub type TrackedKeys = BTreeMap<Vec<u8>, Render>;
+
+/// An entry of the service storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+    /// The state key of the entry
+    pub key: Vec<u8>,
+
+    /// The original key, if known
+    pub original: Option<Vec<u8>>,
+
+    /// The encoded value
+    pub value: Vec<u8>,
+}
+
+impl StorageEntry {
+    /// Decode the value
+    pub fn decode<V: serde::de::DeserializeOwned>(&self) -> Option<V> {
+        codec::decode(&self.value).ok()
+    }
+}
+
+/// Storage of a service account
+#[derive(Clone, Copy)]
+pub struct Storage<'a> {
+    /// The service of the storage
+    service: ServiceId,
+
+    /// The service account, if it exists
+    account: Option<&'a ServiceAccount>,
+
+    /// The tracked original keys
+    keys: &'a TrackedKeys,
+}
+
+impl<'a> Storage<'a> {
+    /// Create the storage view of a service
+    pub(crate) fn new(
+        service: ServiceId,
+        account: Option<&'a ServiceAccount>,
+        keys: &'a TrackedKeys,
+    ) -> Self {
+        Self {
+            service,
+            account,
+            keys,
+        }
+    }
+
+    /// Get the decoded value of a key
+    pub fn get<V: serde::de::DeserializeOwned>(&self, key: &[u8]) -> Option<V> {
+        codec::decode(self.get_raw(key)?).ok()
+    }
+
+    /// Get the encoded value of a key
+    pub fn get_raw(&self, key: &[u8]) -> Option<&'a [u8]> {
+        let key = key::storage(self.service, key);
+        self.account?.storage.get(key.as_ref()).map(Vec::as_slice)
+    }
+
+    /// If the storage has a key
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.get_raw(key).is_some()
+    }
+
+    /// Iterate over the entries, by the state key
+    pub fn iter(&self) -> impl Iterator<Item = StorageEntry> + 'a {
+        let service = self.service;
+        let keys = self.keys;
+        self.account
+            .into_iter()
+            .flat_map(|account| account.storage.iter())
+            .map(move |(key, value)| StorageEntry {
+                key: key.clone(),
+                original: keys
+                    .keys()
+                    .find(|original| key::storage(service, original).as_slice() == key)
+                    .cloned(),
+                value: value.clone(),
+            })
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.account.map_or(0, |account| account.storage.len())
+    }
+
+    /// If the storage is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Storage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "storage of service {}: {} entries",
+            self.service,
+            self.len()
+        )?;
+        for entry in self.iter() {
+            let (key, render) = match &entry.original {
+                Some(original) => (diff::readable(original), self.keys[original]),
+                None => (diff::hex(&entry.key), diff::readable as Render),
+            };
+            writeln!(f, "  {key} => {}", render(&entry.value))?;
+        }
+        Ok(())
+    }
+}
+
+impl Jam {
+    /// Get the storage of a service
+    pub fn storage(&self, service: ServiceId) -> Storage<'_> {
+        Storage::new(service, self.chain.accounts.get(&service), &self.keys)
+    }
+
+    /// Get the decoded storage value of a service
+    pub fn get_storage<V: serde::de::DeserializeOwned>(
+        &self,
+        service: ServiceId,
+        key: &[u8],
+    ) -> Option<V> {
+        self.storage(service).get(key)
+    }
+
+    /// Track an original storage key, to name it in the storage entries
+    pub fn track_storage_key(&mut self, key: &[u8]) {
+        self.keys.insert(key.to_vec(), diff::readable);
+    }
+
+    /// Track an original storage key with the type of its value, to name it
+    /// and decode its value in the storage entries and the state diffs
+    pub fn track_storage<V: DeserializeOwned + fmt::Debug>(&mut self, key: &[u8]) {
+        self.keys.insert(key.to_vec(), self::decoded::<V>);
+    }
+
+    /// Dump the storage of a service
+    pub fn dump_storage(&self, service: ServiceId) -> String {
+        self.storage(service).to_string()
+    }
+}
+
+impl ExecutionInfo {
+    /// Get the storage of a service after the execution
+    pub fn storage(&self, service: ServiceId) -> Storage<'_> {
+        Storage::new(service, self.accounts.get(&service), &self.keys)
+    }
+
+    /// Get the decoded storage value of a service after the execution
+    pub fn get_storage<V: serde::de::DeserializeOwned>(
+        &self,
+        service: ServiceId,
+        key: &[u8],
+    ) -> Option<V> {
+        self.storage(service).get(key)
+    }
+
+    /// Dump the storage of a service after the execution
+    pub fn dump_storage(&self, service: ServiceId) -> String {
+        self.storage(service).to_string()
+    }
+}
+
+/// Render a value decoded as `V`, or the raw bytes if it does not decode
+fn decoded<V: DeserializeOwned + fmt::Debug>(value: &[u8]) -> String {
+    codec::decode::<V>(value)
+        .map(|value| format!("{value:?}"))
+        .unwrap_or_else(|_| diff::readable(value))
+}
@@ -2,6 +2,8 @@
 
 use anyhow::{Context, Result};
 use cjam::ModuleType;
+use service::{OpaqueHash, service::WorkPackage};
+use spacevm::profile::Symbols;
 use tracing_subscriber::EnvFilter;
 
 /// Initialize the logger
@@ -25,6 +27,21 @@ pub fn load_service(package: &str) -> Result<Vec<u8>> {
     std::fs::read(&target).context(format!("Failed to read {}", target.display()))
 }
 
+/// Load the function symbols of the service from its `.pvm` blob
+pub fn load_symbols(package: &str) -> Result<Symbols> {
+    let target = etc::find_up("target")
+        .expect("Failed to find target directory")
+        .join("jam")
+        .join(format!("{package}.pvm"));
+
+    Symbols::load(&target)
+}
+
+/// Compute the hash of a work package
+pub fn package_hash(work: &WorkPackage) -> Result<OpaqueHash> {
+    Ok(service::blake2b(&codec::encode(work)?))
+}
+
 /// Build the service
 pub fn build_service(package: &str, module: Option<ModuleType>) {
     cjam::util::build(package, module).expect("Failed to build service");
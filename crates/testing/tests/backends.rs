@@ -0,0 +1,48 @@
+//! Tests of the backends of the native library
+#![cfg(not(feature = "pure"))]
+
+use common::SERVICE;
+use jade_testing::{Backend, ExecutionInfo};
+
+mod common;
+
+/// Run the suite on a backend
+fn suite(backend: Backend) -> Vec<ExecutionInfo> {
+    let mut jam = common::jam().with_backend(backend);
+    jam.add_service(SERVICE + 1, common::writer(b"total", b"100"));
+    jam.add_service(SERVICE + 2, common::fetcher(0, 0, 64));
+
+    [SERVICE, SERVICE + 1, SERVICE + 2]
+        .into_iter()
+        .map(|service| jam.execute(service, vec![1, 2, 3]).unwrap())
+        .collect()
+}
+
+#[test]
+fn run_the_same_on_both_backends() {
+    let interpreted = self::suite(Backend::Interpreter);
+    let compiled = self::suite(Backend::Compiler);
+    for (interpreted, compiled) in interpreted.iter().zip(&compiled) {
+        assert_eq!(interpreted.results, compiled.results);
+        assert_eq!(interpreted.refine_gas, compiled.refine_gas);
+        assert_eq!(interpreted.accumulate_gas, compiled.accumulate_gas);
+        assert_eq!(interpreted.accounts, compiled.accounts);
+    }
+}
+
+#[test]
+fn reject_the_extrinsics() {
+    let mut jam = common::jam();
+    jam.pack_with_extrinsics(SERVICE, vec![], vec![b"signature".to_vec()])
+        .unwrap();
+    let package = jam.build().unwrap();
+
+    let error = jam.refine(&package, vec![]).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<spacevm::Error>(),
+        Some(&spacevm::Error::Unsupported {
+            call: "refine",
+            feature: "extrinsics",
+        })
+    );
+}
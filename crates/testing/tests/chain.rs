@@ -0,0 +1,109 @@
+//! Tests of the simulated chain
+
+use jade_testing::{ChainConfig, Jam};
+use service::ServiceId;
+
+/// The service of the tests
+const SERVICE: ServiceId = 501;
+
+/// Produce blocks and collect the entropy of their heads
+fn entropy(jam: &mut Jam, blocks: usize) -> Vec<[u8; 32]> {
+    (0..blocks).map(|_| jam.produce_block().entropy).collect()
+}
+
+#[test]
+fn replay_the_entropy_of_a_seed() {
+    let expected = self::entropy(&mut Jam::default().with_seed(7), 20);
+    assert_eq!(
+        self::entropy(&mut Jam::default().with_seed(7), 20),
+        expected
+    );
+    assert_ne!(
+        self::entropy(&mut Jam::default().with_seed(8), 20),
+        expected
+    );
+
+    // the replayed entropy overrides the seed
+    let mut jam = Jam::default().with_seed(8);
+    jam.replay_entropy(expected.clone());
+    assert_eq!(self::entropy(&mut jam, 20), expected);
+}
+
+#[test]
+fn advance_the_slots() {
+    let mut jam = Jam::default();
+    assert_eq!(jam.advance_slots(0).slot, 0);
+    assert_eq!(jam.advance_slots(5).slot, 5);
+    assert_eq!(jam.produce_block().slot, 6);
+    for _ in 0..3 {
+        jam.produce_block();
+    }
+
+    // the finalized block trails the best block
+    let chain = jam.chain();
+    assert_eq!(chain.best.slot, 9);
+    assert_eq!(chain.finalized.slot, 7);
+    assert_eq!(chain.refine_context(vec![]).lookup_anchor_slot, 7);
+}
+
+#[test]
+fn rotate_the_entropy_of_a_seed() {
+    let config = ChainConfig::default().with_epoch_length(4);
+    let mut jam = Jam::default().with_config(config).with_seed(7);
+    let seeded = jam.chain().entropy;
+    assert_eq!(Jam::default().with_seed(7).chain().entropy, seeded);
+
+    // the buffer only rotates on the epoch boundaries
+    jam.advance_slots(3);
+    let entropy = jam.chain().entropy;
+    assert_eq!(entropy[1..], seeded[1..]);
+    jam.produce_block();
+    assert_eq!(jam.chain().entropy[1..], [entropy[0], seeded[1], seeded[2]]);
+
+    // skipping two epochs rotates twice
+    let entropy = jam.chain().entropy;
+    jam.advance_slots(8);
+    assert_eq!(
+        jam.chain().entropy[1..],
+        [entropy[0], entropy[0], entropy[1]]
+    );
+}
+
+#[test]
+fn rotate_the_authorizer_queues() {
+    let queue = (0..80).map(|index| [index; 32]).collect::<Vec<_>>();
+    let mut jam = Jam::default().with_auth_queue(1, queue).unwrap();
+    assert!(!jam.chain().authorized(1, &[0; 32]));
+
+    // the authorizer of every slot joins the pool
+    jam.advance_slots(3);
+    assert_eq!(jam.chain().pools[1], [[1; 32], [2; 32], [3; 32]]);
+    assert!(jam.chain().authorized(1, &[2; 32]));
+    assert!(jam.chain().pools[0].is_empty());
+
+    // only the last `auth_pool` authorizers stay in the pool
+    jam.advance_slots(10);
+    let pool = (6..=13).map(|index| [index; 32]).collect::<Vec<_>>();
+    assert_eq!(jam.chain().pools[1], pool);
+
+    // empty pools only accept any authorizer once opened
+    assert!(!jam.chain().authorized(0, &[0; 32]));
+    assert!(jam.with_open_pools().chain().authorized(0, &[0; 32]));
+}
+
+#[test]
+fn expire_a_preimage() {
+    let mut jam = Jam::default();
+    let hash = jam.add_preimage(SERVICE, b"vesting".to_vec());
+    jam.expire_preimage(SERVICE, hash, 3).unwrap();
+    assert!(jam.expire_preimage(SERVICE, hash, 3).is_err());
+
+    jam.advance_slots(2);
+    assert!(jam.chain().available(SERVICE, &hash));
+    jam.produce_block();
+    assert!(!jam.chain().available(SERVICE, &hash));
+
+    // the preimage is kept until the service forgets it
+    jam.advance_slots(100);
+    assert!(jam.chain().accounts[&SERVICE].preimage.contains_key(&hash));
+}
@@ -0,0 +1,166 @@
+//! Services assembled for the tests of the testing environment
+//!
+//! Each test crate only uses a part of the helpers.
+#![allow(dead_code)]
+
+use jade_testing::Jam;
+use service::ServiceId;
+
+/// The authorizer of the tests
+pub const AUTHORIZER: ServiceId = 500;
+
+/// The service of the tests
+pub const SERVICE: ServiceId = 501;
+
+/// The address of the read-only data of the services
+pub const RO: u32 = 1 << 16;
+
+/// The address of the read-write data of the services without read-only data
+pub const RW: u32 = 2 << 16;
+
+/// `jump_ind ra`, halts the program
+pub const HALT: &[u8] = &[50, 0];
+
+/// `trap`, panics the program
+pub const TRAP: &[u8] = &[0];
+
+/// `fallthrough` padded to the accumulate entry point
+pub const PADDING: &[u8] = &[1, 0, 0, 0, 0];
+
+/// `fallthrough` padded to the accumulate entry point after a halt
+pub const HALT_PADDING: &[u8] = &[1, 0, 0];
+
+/// Assemble a service code blob without metadata
+///
+/// Refine and is_authorized enter at the program counter 0, accumulate at 5.
+pub fn service(instructions: &[&[u8]], ro: &[u8]) -> Vec<u8> {
+    let (mut code, mut mask) = (Vec::new(), Vec::new());
+    for instruction in instructions {
+        mask.push(true);
+        mask.extend(vec![false; instruction.len() - 1]);
+        code.extend_from_slice(instruction);
+    }
+
+    assert!(code.len() < 128);
+    let mut program = vec![0, 0, code.len() as u8];
+    program.extend(&code);
+    program.extend(mask.chunks(8).map(|bits| {
+        bits.iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << i))
+    }));
+
+    // no metadata, 4 pages of read-write data
+    let mut blob = vec![0];
+    blob.extend(&(ro.len() as u32).to_le_bytes()[..3]);
+    blob.extend([0, 0, 0]);
+    blob.extend(4u16.to_le_bytes());
+    blob.extend(&4096u32.to_le_bytes()[..3]);
+    blob.extend(ro);
+    blob.extend((program.len() as u32).to_le_bytes());
+    blob.extend(program);
+    blob
+}
+
+/// `load_imm reg, value`
+pub fn load(reg: u8, value: u32) -> Vec<u8> {
+    let mut instruction = vec![51, reg];
+    instruction.extend(value.to_le_bytes());
+    instruction
+}
+
+/// `jump offset`, relative to the instruction
+pub fn jump(offset: u8) -> Vec<u8> {
+    vec![40, offset]
+}
+
+/// `ecalli id`, calls a host function
+pub fn ecalli(id: u8) -> Vec<u8> {
+    vec![10, id]
+}
+
+/// A service which halts right away, outputting its arguments
+pub fn echo() -> Vec<u8> {
+    self::service(&[PADDING, HALT], &[])
+}
+
+/// A service which outputs the `len` bytes fetched by `kind` and `index` in
+/// refine
+pub fn fetcher(kind: u32, index: u32, len: u32) -> Vec<u8> {
+    self::service(
+        &[
+            &self::jump(7),
+            HALT_PADDING,
+            HALT,
+            &self::load(7, RW),
+            &self::load(8, 0),
+            &self::load(9, len),
+            &self::load(10, kind),
+            &self::load(11, index),
+            &self::ecalli(1),
+            &self::load(7, RW),
+            &self::load(8, len),
+            HALT,
+        ],
+        &[],
+    )
+}
+
+/// A service which exports `segment` in refine
+pub fn exporter(segment: &[u8]) -> Vec<u8> {
+    self::service(
+        &[
+            &self::jump(7),
+            HALT_PADDING,
+            HALT,
+            &self::load(7, RO),
+            &self::load(8, segment.len() as u32),
+            &self::ecalli(7),
+            &self::load(8, 0),
+            HALT,
+        ],
+        segment,
+    )
+}
+
+/// A service which writes `value` to `key` in accumulate
+pub fn writer(key: &[u8], value: &[u8]) -> Vec<u8> {
+    let len = key.len() as u32;
+    self::service(
+        &[
+            HALT,
+            HALT_PADDING,
+            &self::load(7, RO),
+            &self::load(8, len),
+            &self::load(9, RO + len),
+            &self::load(10, value.len() as u32),
+            &self::ecalli(4),
+            HALT,
+        ],
+        &[key, value].concat(),
+    )
+}
+
+/// A service which assigns `queue` to `core` in accumulate, handing the core
+/// over to `next`
+pub fn assigner(core: u16, queue: &[[u8; 32]], next: ServiceId) -> Vec<u8> {
+    self::service(
+        &[
+            HALT,
+            HALT_PADDING,
+            &self::load(7, core as u32),
+            &self::load(8, RO),
+            &self::load(9, next),
+            &self::ecalli(15),
+            HALT,
+        ],
+        &queue.concat(),
+    )
+}
+
+/// An environment with the echo service behind the echo authorizer
+pub fn jam() -> Jam {
+    let mut jam = Jam::default().with_auth(AUTHORIZER, self::echo());
+    jam.add_service(SERVICE, self::echo());
+    jam
+}
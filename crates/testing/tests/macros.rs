@@ -0,0 +1,120 @@
+//! Failure messages of the assertion macros
+
+use jade_testing::{
+    Account, ExecutionInfo, WorkResult, assert_balance_changed, assert_gas, assert_out_of_gas,
+    assert_output, assert_service_created, assert_storage, assert_storage_absent, assert_transfer,
+    assert_work_result, key,
+};
+use service::{ServiceId, vm::DeferredTransfer};
+
+/// The service of the tests
+const SERVICE: ServiceId = 501;
+
+/// An execution after which the service stores `value` at `key`
+fn stored(key: &[u8], value: u64) -> ExecutionInfo {
+    let mut account = Account::default();
+    account.storage.insert(
+        key::storage(SERVICE, key).to_vec(),
+        codec::encode(&value).unwrap(),
+    );
+    ExecutionInfo {
+        accounts: [(SERVICE, account)].into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pass_on_the_expected_state() {
+    let info = self::stored(b"total", 100);
+    assert_storage!(info, SERVICE, b"total", 100u64);
+    assert_storage_absent!(info, SERVICE, b"pending");
+    assert_balance_changed!(info, SERVICE, 0);
+    assert_gas!(info, ..=0);
+}
+
+#[test]
+#[should_panic(expected = "storage \"total\" of service 501 is Some(100), expected 99")]
+fn fail_on_another_storage_value() {
+    assert_storage!(self::stored(b"total", 100), SERVICE, b"total", 99u64);
+}
+
+#[test]
+#[should_panic(expected = "storage \"total\" of service 501 is present")]
+fn fail_on_a_present_storage_key() {
+    assert_storage_absent!(self::stored(b"total", 100), SERVICE, b"total");
+}
+
+#[test]
+#[should_panic(expected = "work item 1 refined to None")]
+fn fail_on_a_missing_work_item() {
+    let info = ExecutionInfo {
+        results: vec![WorkResult::Panic],
+        ..Default::default()
+    };
+    assert_work_result!(info, 1, WorkResult::Ok(_));
+}
+
+#[test]
+#[should_panic(expected = "work item 0 refined to Some(Panic), expected out of gas")]
+fn fail_on_a_refine_with_gas_left() {
+    let info = ExecutionInfo {
+        results: vec![WorkResult::Panic],
+        ..Default::default()
+    };
+    assert_out_of_gas!(info);
+}
+
+#[test]
+#[should_panic(expected = "balance of service 501 changed by +0, expected -10")]
+fn fail_on_another_balance_change() {
+    assert_balance_changed!(ExecutionInfo::default(), SERVICE, -10);
+}
+
+#[test]
+#[should_panic(expected = "service 502 is not created")]
+fn fail_on_a_missing_service() {
+    assert_service_created!(ExecutionInfo::default(), SERVICE + 1);
+}
+
+#[test]
+#[should_panic(expected = "no transfer of 10 sent to 502")]
+fn fail_on_another_transfer_amount() {
+    let mut info = ExecutionInfo::default();
+    info.services
+        .entry(SERVICE)
+        .or_default()
+        .transfers
+        .push(DeferredTransfer {
+            sender: SERVICE,
+            recipient: SERVICE + 1,
+            amount: 5,
+            memo: Vec::new(),
+            gas_limit: 0,
+        });
+
+    assert_transfer!(info, SERVICE + 1);
+    assert_transfer!(info, SERVICE + 1, 10);
+}
+
+#[test]
+#[should_panic(expected = "output of service 501 is None")]
+fn fail_on_a_missing_output() {
+    assert_output!(ExecutionInfo::default(), SERVICE, [0; 32]);
+}
+
+#[test]
+#[should_panic(expected = "accumulate gas 100 is not within ..=10")]
+fn fail_on_the_gas_over_the_bound() {
+    let info = ExecutionInfo {
+        accumulate_gas: 100,
+        ..Default::default()
+    };
+    assert_gas!(info, accumulate, ..=10);
+}
+
+#[cfg(feature = "pure")]
+#[test]
+#[should_panic(expected = "no guest log contains \"minting\"")]
+fn fail_on_a_missing_log() {
+    jade_testing::assert_log!(ExecutionInfo::default(), "minting");
+}
@@ -0,0 +1,422 @@
+//! Tests of the testing environment on the pure backend
+#![cfg(feature = "pure")]
+
+use common::{HALT, HALT_PADDING, RO, SERVICE, TRAP};
+use jade_testing::{
+    ChainConfig, Change, Constants, Jam, WorkResult, assert_out_of_gas, assert_work_result, util,
+};
+use service::{
+    CORES_COUNT, OpaqueHash, ServiceId,
+    api::Reason,
+    vm::{AccumulateItem, DeferredTransfer, RefineParams},
+};
+
+mod common;
+
+#[test]
+fn refine_on_the_configured_core() {
+    let mut jam = common::jam().with_core(1);
+    let info = jam.execute(SERVICE, vec![]).unwrap();
+    assert_eq!(info.reports[0].core, 1);
+
+    let WorkResult::Ok(output) = &info.results[0] else {
+        panic!("refine failed: {:?}", info.results);
+    };
+    let params = codec::decode::<RefineParams>(output).unwrap();
+    assert_eq!(params.core, 1);
+    assert_eq!(params.id, SERVICE);
+}
+
+#[test]
+fn fetch_an_extrinsic_in_refine() {
+    let extrinsic = b"signature".to_vec();
+    let len = extrinsic.len() as u32;
+    let mut jam = common::jam();
+    jam.add_service(SERVICE + 1, common::fetcher(4, 0, len));
+
+    jam.pack_with_extrinsics(SERVICE + 1, vec![], vec![extrinsic.clone()])
+        .unwrap();
+    let package = jam.build().unwrap();
+    jam.submit(0, package).unwrap();
+    let info = jam.execute_block().unwrap();
+    assert_eq!(info.results, vec![WorkResult::Ok(extrinsic)]);
+}
+
+#[test]
+fn import_a_segment_of_another_package() {
+    let mut jam = common::jam();
+    jam.add_service(SERVICE + 1, common::exporter(b"segment"));
+    jam.add_service(SERVICE + 2, common::fetcher(6, 0, 7));
+
+    let exporter = self::export(&mut jam, SERVICE + 1);
+    let import = jam.segments().import(&exporter, 0).unwrap();
+    let item = jam.item(SERVICE + 2, vec![]).unwrap().with_import(import);
+    jam.pack_item(item);
+    let package = jam.build().unwrap();
+    jam.submit(0, package).unwrap();
+    let info = jam.execute_block().unwrap();
+    assert_eq!(info.results, vec![WorkResult::Ok(b"segment".to_vec())]);
+}
+
+#[test]
+fn import_by_the_segment_root_and_the_package_hash() {
+    let mut jam = common::jam();
+    jam.add_service(SERVICE + 1, common::exporter(b"segment"));
+    jam.add_service(SERVICE + 2, common::exporter(b"another"));
+    jam.add_service(SERVICE + 3, common::fetcher(6, 0, 7));
+    jam.add_service(SERVICE + 4, common::fetcher(6, 1, 7));
+
+    let first = self::export(&mut jam, SERVICE + 1);
+    let second = self::export(&mut jam, SERVICE + 2);
+    let root = jam.segments().import(&first, 0).unwrap();
+    for service in [SERVICE + 3, SERVICE + 4] {
+        let item = jam
+            .item(service, vec![])
+            .unwrap()
+            .with_import(root.clone())
+            .with_package_import(second, 0);
+        jam.pack_item(item);
+    }
+
+    let package = jam.build().unwrap();
+    jam.submit(0, package).unwrap();
+    let info = jam.execute_block().unwrap();
+    assert_eq!(
+        info.results,
+        vec![
+            WorkResult::Ok(b"segment".to_vec()),
+            WorkResult::Ok(b"another".to_vec())
+        ]
+    );
+}
+
+/// Refine a package exporting a segment of the service, returns its hash
+fn export(jam: &mut Jam, service: ServiceId) -> OpaqueHash {
+    let item = jam.item(service, vec![]).unwrap().with_export_count(1);
+    jam.pack_item(item);
+    let package = jam.build().unwrap();
+    jam.submit(0, package).unwrap();
+    jam.execute_block().unwrap().reports[0].package
+}
+
+#[test]
+fn queue_a_package_until_its_prerequisite() {
+    let mut jam = common::jam();
+    let first = jam.send(SERVICE, vec![0]).unwrap();
+    jam.add_prerequisite(util::package_hash(&first).unwrap());
+    let second = jam.send(SERVICE, vec![1]).unwrap();
+
+    jam.submit(0, second.clone()).unwrap();
+    jam.execute_block().unwrap();
+    assert_eq!(jam.queued().len(), 1);
+    assert_eq!(
+        jam.queued()[0].package,
+        util::package_hash(&second).unwrap()
+    );
+
+    // the queued package is released along with its prerequisite
+    jam.submit(0, first).unwrap();
+    jam.execute_block().unwrap();
+    assert!(jam.queued().is_empty());
+}
+
+#[test]
+fn report_the_results_of_the_items() {
+    let mut jam = common::jam();
+    jam.pack(SERVICE, vec![]).unwrap();
+    let item = jam.item(SERVICE, vec![]).unwrap().with_refine_gas(1);
+    jam.pack_item(item);
+    let item = jam.item(SERVICE, vec![]).unwrap().with_export_count(1);
+    jam.pack_item(item);
+    let package = jam.build().unwrap();
+    jam.submit(0, package).unwrap();
+
+    let info = jam.execute_block().unwrap();
+    assert_work_result!(info, WorkResult::Ok(_));
+    assert_out_of_gas!(info, 1);
+    assert_work_result!(info, 2, WorkResult::InvalidExports);
+}
+
+#[test]
+fn drop_an_unauthorized_package() {
+    let mut jam = common::jam().with_auth_pool(1, vec![[1; 32]]).unwrap();
+    let first = jam.send(SERVICE, vec![0]).unwrap();
+    let second = jam.send(SERVICE, vec![1]).unwrap();
+    jam.submit(0, first.clone()).unwrap();
+    jam.submit(1, second).unwrap();
+
+    let info = jam.execute_block().unwrap();
+    assert_eq!(info.reports.len(), 1);
+    assert_eq!(info.reports[0].package, util::package_hash(&first).unwrap());
+    assert!(jam.submitted().is_empty());
+}
+
+#[test]
+fn keep_the_packages_of_a_failed_block() {
+    let mut jam = common::jam();
+    let package = jam.send(SERVICE, vec![]).unwrap();
+    jam.submit(0, package.clone()).unwrap();
+
+    // the anchor of the package falls out of the recent blocks
+    let recent = jam.chain().config.constants.recent_history;
+    for _ in 0..recent {
+        jam.produce_block();
+    }
+
+    assert!(jam.execute_block().is_err());
+    assert_eq!(jam.submitted().get(&0), Some(&package));
+}
+
+#[test]
+fn reject_a_core_out_of_range() {
+    let mut jam = common::jam();
+    let package = jam.send(SERVICE, vec![]).unwrap();
+    assert!(jam.submit(CORES_COUNT as u16, package).is_err());
+
+    let core = CORES_COUNT as u16;
+    assert!(common::jam().with_auth_pool(core, vec![]).is_err());
+    assert!(common::jam().with_auth_queue(core, vec![]).is_err());
+    assert!(common::jam().with_assigner(core, SERVICE).is_err());
+}
+
+#[test]
+fn consume_the_authorizer_of_a_package() {
+    let mut jam = common::jam().with_auth_queue(0, vec![]).unwrap();
+    jam.execute(SERVICE, vec![]).unwrap();
+    assert!(jam.chain().pools[0].is_empty());
+    assert!(jam.execute(SERVICE, vec![]).is_err());
+
+    // the queue refills the pool of the other core
+    let mut jam = jam.with_core(1);
+    let pool = jam.chain().pools[1].clone();
+    jam.execute(SERVICE, vec![]).unwrap();
+    assert_eq!(jam.chain().pools[1], pool);
+}
+
+#[test]
+fn assign_the_authorizer_queue() {
+    let queue = vec![[7; 32]; ChainConfig::default().constants.auth_queue as usize];
+    let mut jam = common::jam().with_assigner(1, SERVICE + 1).unwrap();
+    jam.add_service(SERVICE + 1, common::assigner(1, &queue, SERVICE + 2));
+    jam.execute(SERVICE + 1, vec![]).unwrap();
+    assert_eq!(jam.chain().queues[1], queue);
+    assert_eq!(jam.chain().privileges.assign[1], SERVICE + 2);
+
+    // the new queue rotates into the pool with the next block
+    jam.produce_block();
+    assert!(jam.chain().pools[1].contains(&[7; 32]));
+
+    // the core is handed over, the service can't assign it again
+    jam.execute(SERVICE + 1, vec![]).unwrap();
+    assert_eq!(jam.chain().privileges.assign[1], SERVICE + 2);
+}
+
+#[test]
+fn serve_the_constants_of_the_presets() {
+    // the full constants don't match the cores and validators of the types
+    assert!(ChainConfig::new(Constants::tiny()).is_ok());
+    assert!(ChainConfig::new(Constants::full()).is_err());
+
+    for config in [ChainConfig::tiny(), ChainConfig::full()] {
+        assert_eq!(config.constants.cores as usize, CORES_COUNT);
+        assert_eq!(
+            config.constants.validators as usize,
+            config.validators.len()
+        );
+
+        let constants = config.constants.encode();
+        let mut jam = common::jam().with_config(config);
+        jam.add_service(SERVICE + 1, common::fetcher(0, 0, constants.len() as u32));
+        let info = jam.execute(SERVICE + 1, vec![]).unwrap();
+        assert_eq!(info.results, vec![WorkResult::Ok(constants)]);
+    }
+}
+
+#[test]
+fn keep_the_checkpoint_of_a_failed_accumulation() {
+    let mut jam = common::jam();
+    jam.add_service(
+        SERVICE + 1,
+        common::service(&[HALT, HALT_PADDING, TRAP], &[]),
+    );
+
+    let info = jam.execute(SERVICE + 1, vec![]).unwrap();
+    let accumulation = info.service(SERVICE + 1).unwrap();
+    assert!(matches!(accumulation.reason, Reason::Panic(_)));
+    assert!(info.diff.is_empty(), "{}", info.describe());
+}
+
+#[test]
+fn receive_the_memo_of_a_transfer() {
+    let memo = [b"hello".as_slice(), &[0; 123]].concat();
+    let transfer = DeferredTransfer {
+        sender: SERVICE + 1,
+        recipient: SERVICE + 2,
+        amount: 10,
+        memo: memo.clone(),
+        gas_limit: 10_000,
+    };
+    let item = codec::encode(&AccumulateItem::Transfer(transfer.clone())).unwrap();
+    let len = item.len() as u32;
+
+    let mut jam = common::jam();
+    jam.add_service(
+        SERVICE + 1,
+        common::service(
+            &[
+                HALT,
+                HALT_PADDING,
+                &common::load(7, transfer.recipient),
+                &common::load(8, transfer.amount as u32),
+                &common::load(9, transfer.gas_limit as u32),
+                &common::load(10, RO),
+                &common::ecalli(20),
+                HALT,
+            ],
+            &memo,
+        ),
+    );
+
+    // the read-write data follows the zone of the read-only data
+    let rw = common::RW + (1 << 16);
+    jam.add_service(
+        SERVICE + 2,
+        common::service(
+            &[
+                HALT,
+                HALT_PADDING,
+                &common::load(7, rw),
+                &common::load(8, 0),
+                &common::load(9, len),
+                &common::load(10, 15),
+                &common::load(11, 0),
+                &common::ecalli(1),
+                &common::load(7, RO),
+                &common::load(8, 4),
+                &common::load(9, rw),
+                &common::load(10, len),
+                &common::ecalli(4),
+                HALT,
+            ],
+            b"memo",
+        ),
+    );
+
+    let info = jam.execute(SERVICE + 1, vec![]).unwrap();
+    assert_eq!(info.service(SERVICE + 1).unwrap().transfers, vec![transfer]);
+    assert_eq!(
+        info.storage(SERVICE + 2).get_raw(b"memo"),
+        Some(item.as_slice())
+    );
+}
+
+#[test]
+fn describe_the_decoded_storage() {
+    let mut jam = common::jam();
+    jam.add_service(
+        SERVICE + 1,
+        common::writer(b"total", &codec::encode(&7u64).unwrap()),
+    );
+    jam.add_service(SERVICE + 2, common::writer(b"name", b"jade"));
+    jam.track_storage::<u64>(b"total");
+
+    let info = jam.execute(SERVICE + 1, vec![]).unwrap();
+    let description = info.describe();
+    assert!(
+        description.contains("storage \"total\": added 7"),
+        "{description}"
+    );
+
+    // the values of the untracked keys are printed if they are readable
+    let info = jam.execute(SERVICE + 2, vec![]).unwrap();
+    let description = info.describe();
+    assert!(description.contains(": added \"jade\""), "{description}");
+}
+
+#[test]
+fn restore_a_snapshot() {
+    let mut jam = common::jam();
+    jam.add_service(SERVICE + 1, common::writer(b"key", b"value"));
+    let snapshot = jam.snapshot();
+
+    jam.execute(SERVICE + 1, vec![]).unwrap();
+    assert_eq!(
+        jam.storage(SERVICE + 1).get_raw(b"key"),
+        Some(&b"value"[..])
+    );
+
+    jam.restore(&snapshot);
+    assert!(!jam.storage(SERVICE + 1).contains(b"key"));
+
+    // the write is applied again on top of the restored state
+    let info = jam.execute(SERVICE + 1, vec![]).unwrap();
+    assert!(matches!(
+        info.diff.storage(SERVICE + 1, b"key"),
+        Some(Change::Added(value)) if value == b"value"
+    ));
+}
+
+#[test]
+fn save_and_load_the_state() {
+    let mut jam = common::jam()
+        .with_seed(7)
+        .with_auth_queue(1, vec![[2; 32]])
+        .unwrap()
+        .with_assigner(1, SERVICE)
+        .unwrap();
+    jam.add_service(SERVICE + 1, common::writer(b"key", b"value"));
+    let package = jam.execute(SERVICE + 1, vec![]).unwrap().reports[0].package;
+    let best = jam.produce_block();
+
+    let dir = std::env::temp_dir().join(format!("jade-testing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["state.json", "state.bin"] {
+        let path = dir.join(name);
+        jam.save_state(&path).unwrap();
+
+        let mut loaded = Jam::default().with_state(&path).unwrap();
+        assert_eq!(loaded.advance_slots(0), best);
+        assert_eq!(
+            loaded.storage(SERVICE + 1).get_raw(b"key"),
+            Some(&b"value"[..])
+        );
+
+        // with the authorizers and the privileges
+        let (chain, saved) = (loaded.chain(), jam.chain());
+        assert_eq!(chain.pools, saved.pools);
+        assert_eq!(chain.queues, saved.queues);
+        assert_eq!(chain.privileges, saved.privileges);
+        assert_eq!(chain.entropy, saved.entropy);
+
+        // the chain moves on and loads the state back
+        let mut moved = jam.fork();
+        moved.add_service(SERVICE + 2, common::echo());
+        moved.produce_block();
+        moved.load_state(&path).unwrap();
+        assert_eq!(moved.advance_slots(0), best);
+        assert!(
+            moved.execute(SERVICE + 2, vec![]).is_err(),
+            "service removed"
+        );
+
+        // the accumulated packages are kept, and refine is anchored to the
+        // loaded best block
+        moved.add_prerequisite(package);
+        moved.execute(SERVICE + 1, vec![]).unwrap();
+        assert!(moved.queued().is_empty());
+    }
+
+    // a short entropy buffer is rejected rather than padded
+    let path = dir.join("state.json");
+    let mut state: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    state["entropy"].as_array_mut().unwrap().pop();
+    std::fs::write(&path, state.to_string()).unwrap();
+    let error = Jam::default().with_state(&path).err().unwrap();
+    assert!(
+        error.to_string().contains("invalid entropy buffer"),
+        "{error}"
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
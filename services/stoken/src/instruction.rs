@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
+#[jade::abi]
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum Instruction {
     /// Mint tokens to the given account
@@ -11,7 +11,7 @@ use jade::{
     },
 };
 
-#[jade::refine]
+#[jade::refine(payload = Vec<Instruction>)]
 fn refine(
     _core: u16,
     _index: u16,
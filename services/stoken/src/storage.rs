@@ -4,7 +4,8 @@ use jade::{error, host::storage, prelude::BTreeMap};
 use serde::{Deserialize, Serialize};
 
 /// A map of account IDs to their balances
-#[derive(Serialize, Deserialize, Default)]
+#[jade::storage(key = "holders")]
+#[derive(Serialize, Deserialize, Default, Debug)]
 pub struct Holders {
     inner: BTreeMap<u32, u64>,
 }
@@ -1,6 +1,6 @@
 //! Basic VM tests
 
-use jade::testing::Jam;
+use jade::testing::{Change, Jam, assert_gas, assert_out_of_gas, assert_storage_absent};
 use stoken::{Holders, Instruction, SERVICE};
 
 const AUTHORIZER_ID: u32 = 500;
@@ -14,6 +14,8 @@ fn test_mint() {
     // Set up JAM with authorization using the null authorizer service
     let mut jam = Jam::default().with_auth(AUTHORIZER_ID, nauth::SERVICE.to_vec());
     jam.add_service(SERVICE_ID, SERVICE.to_vec());
+    jam.track_storage::<Holders>(Holders::key());
+    let snapshot = jam.snapshot();
 
     // 1. send a mint instruction
     let amount = 100;
@@ -27,4 +29,56 @@ fn test_mint() {
         .get_storage(SERVICE_ID, Holders::key())
         .expect("failed to get holders");
     assert_eq!(holders.balance(ALICE), amount);
+    let stored = jam.get_storage::<Holders>(SERVICE_ID, Holders::key());
+    assert_eq!(stored.map(|holders| holders.balance(ALICE)), Some(amount));
+
+    // the entries are named by the tracked keys
+    let storage = info.storage(SERVICE_ID);
+    assert!(
+        storage
+            .iter()
+            .any(|entry| entry.original.as_deref() == Some(Holders::key())),
+        "{storage}"
+    );
+
+    // 3. check the state diff
+    assert!(info.diff.created.is_empty());
+    assert_storage_absent!(info, SERVICE_ID, b"missing");
+    assert!(matches!(
+        info.diff.storage(SERVICE_ID, Holders::key()),
+        Some(Change::Added(_))
+    ));
+    assert!(
+        info.describe().contains("inner: {0: 100}"),
+        "{}",
+        info.describe()
+    );
+
+    // 4. the same mint on the same state uses the same gas
+    jam.restore(&snapshot);
+    let again = jam
+        .execute(SERVICE_ID, codec::encode(&instr).unwrap())
+        .expect("failed to execute work item");
+    assert_gas!(again, refine, info.refine_gas..=info.refine_gas);
+    assert_gas!(again, accumulate, info.accumulate_gas..=info.accumulate_gas);
+}
+
+#[test]
+fn test_out_of_gas() {
+    jade::testing::util::init_logger();
+
+    let mut jam = Jam::default()
+        .with_auth(AUTHORIZER_ID, nauth::SERVICE.to_vec())
+        .with_gas_limits(10, 1_000_000);
+    jam.add_service(SERVICE_ID, SERVICE.to_vec());
+
+    // refine runs out of gas, the instruction is not applied
+    let instr = vec![Instruction::Mint {
+        to: ALICE,
+        amount: 100,
+    }];
+    let info = jam
+        .execute(SERVICE_ID, codec::encode(&instr).unwrap())
+        .expect("failed to execute work item");
+    assert_out_of_gas!(info);
 }
@@ -0,0 +1,37 @@
+#![cfg_attr(any(target_arch = "riscv32", target_arch = "riscv64"), no_std)]
+
+use jade::prelude::{AuthContext, AuthTrace, Vec};
+use serde::{Deserialize, Serialize};
+
+/// The authorizer config
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct TokenConfig {
+    /// The token the work packages must carry
+    pub token: Vec<u8>,
+
+    /// The services the work items may refine
+    pub services: Vec<u32>,
+}
+
+#[jade::is_authorized]
+fn is_authorized(context: AuthContext<TokenConfig>) -> Result<AuthTrace, &'static str> {
+    if context.token != context.config.token {
+        return Err("invalid token");
+    }
+
+    let services = &context.config.services;
+    if !context
+        .package
+        .items
+        .iter()
+        .all(|item| services.contains(&item.service))
+    {
+        return Err("service not allowed");
+    }
+
+    Ok(context.core.to_le_bytes().to_vec())
+}
+
+/// The service blob for the token authorizer
+#[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+pub const SERVICE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/service.jam"));
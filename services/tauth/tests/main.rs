@@ -0,0 +1,70 @@
+//! Authorization tests of the token authorizer
+
+use jade::testing::{self, Jam};
+use tauth::{SERVICE, TokenConfig};
+
+const AUTHORIZER: u32 = 500;
+const SERVICE_ID: u32 = 501;
+
+/// An environment with the token authorizer configured with `config`
+fn jam(token: &[u8], config: &TokenConfig) -> Jam {
+    let mut jam = Jam::default()
+        .with_auth(AUTHORIZER, SERVICE.to_vec())
+        .with_auth_token(token.to_vec())
+        .with_auth_config(codec::encode(config).unwrap());
+    jam.add_service(SERVICE_ID, nauth::SERVICE.to_vec());
+    jam
+}
+
+#[test]
+fn test_authorize_with_token() {
+    testing::util::init_logger();
+
+    let config = TokenConfig {
+        token: b"secret".to_vec(),
+        services: vec![SERVICE_ID],
+    };
+    let mut jam = self::jam(b"secret", &config);
+    let package = jam
+        .send(SERVICE_ID, vec![])
+        .expect("failed to send work item");
+
+    // the trace is the core of the invocation
+    let result = jam.authorize(&package, 1).expect("failed to authorize");
+    assert!(result.is_ok(), "{:?}", result);
+    assert_eq!(result.data, 1u16.to_le_bytes());
+}
+
+#[test]
+fn test_reject_invalid_token() {
+    testing::util::init_logger();
+
+    let config = TokenConfig {
+        token: b"secret".to_vec(),
+        services: vec![SERVICE_ID],
+    };
+    let mut jam = self::jam(b"guess", &config);
+    let package = jam
+        .send(SERVICE_ID, vec![])
+        .expect("failed to send work item");
+
+    let result = jam.authorize(&package, 0).expect("failed to authorize");
+    assert!(!result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_reject_service_not_allowed() {
+    testing::util::init_logger();
+
+    let config = TokenConfig {
+        token: b"secret".to_vec(),
+        services: vec![],
+    };
+    let mut jam = self::jam(b"secret", &config);
+    let package = jam
+        .send(SERVICE_ID, vec![])
+        .expect("failed to send work item");
+
+    let result = jam.authorize(&package, 0).expect("failed to authorize");
+    assert!(!result.is_ok(), "{:?}", result);
+}